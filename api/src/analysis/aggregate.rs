@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use num_format::{Locale, ToFormattedString};
+
+use crate::analysis::{milli_to_gas, AnalysisError, AnalysisMode, TraceAnalysis};
+use crate::trace::ExecutionTrace;
+
+/// Gas statistics over the analyses of many messages, e.g. all those of a test scenario.
+/// Spans are grouped by name, ignoring their index, so every call to the same actor and method,
+/// or every named span with the same label, contributes to one set of statistics.
+/// Charge statistics are over the total of each charge name in each message.
+#[derive(Clone, Debug, Default)]
+pub struct AggregateAnalysis {
+    messages: usize,
+    // Total milligas of each occurrence of a span, by span name.
+    spans: BTreeMap<String, Vec<u64>>,
+    // Milligas of a charge name in each message in which it was charged, by charge name.
+    charges: BTreeMap<String, Vec<u64>>,
+    // Problems tolerated by lenient analyses, with the index of their message.
+    warnings: Vec<(usize, AnalysisError)>,
+}
+
+/// Statistics of gas amounts, in milligas.
+/// Percentiles are by the nearest-rank method.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GasStats {
+    pub count: usize,
+    pub min: u64,
+    pub max: u64,
+    pub mean: u64,
+    pub p50: u64,
+    pub p95: u64,
+    pub total: u64,
+}
+
+impl GasStats {
+    /// Computes statistics of some samples.
+    pub fn from_samples(samples: &[u64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let sorted = samples.iter().copied().sorted().collect_vec();
+        let percentile = |p: usize| sorted[((sorted.len() * p).div_ceil(100)).max(1) - 1];
+        let total: u64 = sorted.iter().sum();
+        Self {
+            count: sorted.len(),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: total / sorted.len() as u64,
+            p50: percentile(50),
+            p95: percentile(95),
+            total,
+        }
+    }
+}
+
+impl AggregateAnalysis {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Aggregates the analyses of many messages.
+    pub fn from_analyses<'a>(analyses: impl IntoIterator<Item = &'a TraceAnalysis>) -> Self {
+        let mut aggregate = Self::new();
+        for analysis in analyses {
+            aggregate.add(analysis);
+        }
+        aggregate
+    }
+
+    /// Analyses and aggregates the traces of many messages.
+    /// In strict mode, returns an error if any trace's calls and spans are not well nested.
+    /// In lenient mode, such problems are collected as warnings.
+    pub fn from_traces<'a>(
+        traces: impl IntoIterator<Item = &'a ExecutionTrace>,
+        mode: AnalysisMode,
+    ) -> Result<Self, AnalysisError> {
+        let mut aggregate = Self::new();
+        for trace in traces {
+            aggregate.add(&TraceAnalysis::build_with_mode(trace.clone(), mode)?);
+        }
+        Ok(aggregate)
+    }
+
+    /// Adds the analysis of one message, along with any warnings of a lenient analysis.
+    pub fn add(&mut self, analysis: &TraceAnalysis) {
+        self.warnings.extend(analysis.warnings().iter().map(|w| (self.messages, w.clone())));
+        self.messages += 1;
+        for span in analysis.spans() {
+            let total = analysis.total_gas(span).total_milli();
+            self.spans.entry(span.name()).or_default().push(total);
+        }
+        for (name, charge) in analysis.total_gas_by_name(analysis.root()) {
+            self.charges.entry(name).or_default().push(charge.total_milli());
+        }
+    }
+
+    /// The number of messages aggregated.
+    pub fn messages(&self) -> usize {
+        self.messages
+    }
+
+    /// Returns the problems tolerated by lenient analyses, with the index of their message
+    /// in the order aggregated.
+    pub fn warnings(&self) -> &[(usize, AnalysisError)] {
+        &self.warnings
+    }
+
+    /// Returns statistics of the total gas of spans with some name, like "Span(load_deals)".
+    pub fn span_stats(&self, name: &str) -> Option<GasStats> {
+        self.spans.get(name).map(|samples| GasStats::from_samples(samples))
+    }
+
+    /// Returns statistics of the gas of a charge name per message.
+    pub fn charge_stats(&self, name: &str) -> Option<GasStats> {
+        self.charges.get(name).map(|samples| GasStats::from_samples(samples))
+    }
+
+    /// Returns statistics for each span name, in descending order of total gas.
+    pub fn all_span_stats(&self) -> Vec<(String, GasStats)> {
+        sorted_stats(&self.spans)
+    }
+
+    /// Returns statistics for each charge name, in descending order of total gas.
+    pub fn all_charge_stats(&self) -> Vec<(String, GasStats)> {
+        sorted_stats(&self.charges)
+    }
+
+    /// Formats tables of span and charge statistics, with amounts in gas, followed by any
+    /// warnings.
+    pub fn format_table(&self) -> String {
+        let mut table = format!(
+            "Messages: {}\n\n{}\n\n{}",
+            self.messages,
+            format_stats_table("Span", &self.all_span_stats()),
+            format_stats_table("Charge", &self.all_charge_stats()),
+        );
+        if !self.warnings.is_empty() {
+            table += &format!("\n\nWarnings: {}", self.warnings.len());
+            for (message, warning) in &self.warnings {
+                table += &format!("\n  message {}: {}", message, warning);
+            }
+        }
+        table
+    }
+}
+
+fn sorted_stats(samples: &BTreeMap<String, Vec<u64>>) -> Vec<(String, GasStats)> {
+    samples
+        .iter()
+        .map(|(name, samples)| (name.clone(), GasStats::from_samples(samples)))
+        .sorted_by(|a, b| b.1.total.cmp(&a.1.total))
+        .collect()
+}
+
+fn format_stats_table(heading: &str, stats: &[(String, GasStats)]) -> String {
+    let gas = |milli: u64| milli_to_gas(milli).to_formatted_string(&Locale::en);
+    let mut rows = vec![["count", "min", "max", "mean", "p50", "p95", "total"].map(str::to_string)];
+    let mut names = vec![heading.to_string()];
+    for (name, s) in stats {
+        names.push(name.clone());
+        rows.push([
+            s.count.to_string(),
+            gas(s.min),
+            gas(s.max),
+            gas(s.mean),
+            gas(s.p50),
+            gas(s.p95),
+            gas(s.total),
+        ]);
+    }
+    let name_width = names.iter().map(|n| n.len()).max().unwrap_or(0);
+    let widths: Vec<usize> =
+        (0..7).map(|col| rows.iter().map(|r| r[col].len()).max().unwrap_or(0)).collect();
+    names
+        .iter()
+        .zip(rows.iter())
+        .map(|(name, row)| {
+            let cells = row.iter().zip(&widths).map(|(cell, w)| format!("{:>w$}", cell)).join("  ");
+            format!("{:<w$}  {}", name, cells, w = name_width)
+        })
+        .join("\n")
+}
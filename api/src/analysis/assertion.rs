@@ -0,0 +1,257 @@
+use std::fmt::Display;
+
+use fvm_shared::address::Address;
+use fvm_shared::{ActorID, MethodNum};
+use itertools::Itertools;
+use num_format::{Locale, ToFormattedString};
+
+use crate::analysis::{format_gas_bits, GasCharge, GasFormat, Span, SpanKind, TraceAnalysis};
+
+/// The spans to which a gas assertion applies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpanSelector {
+    /// Named spans opened with some label, e.g. "verify_deals".
+    Label(String),
+    /// Calls to an actor, by the address to which they were made, and method.
+    /// Addresses are not resolved, so calls made to an actor's ID address don't match its
+    /// robust address, nor vice versa.
+    Call { to: Address, method: MethodNum },
+    /// The whole message.
+    Message,
+}
+
+impl SpanSelector {
+    /// Selects calls to an actor's ID address with a method.
+    pub fn call(to: ActorID, method: MethodNum) -> Self {
+        SpanSelector::Call { to: Address::new_id(to), method }
+    }
+
+    /// Selects calls to an address with a method.
+    pub fn call_to(to: Address, method: MethodNum) -> Self {
+        SpanSelector::Call { to, method }
+    }
+
+    fn matches(&self, span: &Span) -> bool {
+        match self {
+            SpanSelector::Label(label) => span.kind() == SpanKind::Named && span.label() == label,
+            SpanSelector::Call { to, method } => {
+                span.call().is_some_and(|call| call.to == *to && call.method == *method)
+            }
+            SpanSelector::Message => span.kind() == SpanKind::Root,
+        }
+    }
+}
+
+impl From<&str> for SpanSelector {
+    fn from(label: &str) -> Self {
+        SpanSelector::Label(label.to_string())
+    }
+}
+
+impl Display for SpanSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpanSelector::Label(label) => write!(f, "spans labelled {:?}", label),
+            SpanSelector::Call { to, method } => write!(f, "calls to {} method {}", to, method),
+            SpanSelector::Message => write!(f, "the message"),
+        }
+    }
+}
+
+/// Which gas of a span an assertion bounds.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GasMeasure {
+    /// Gas consumed directly by the span, excluding by its children.
+    SelfGas,
+    /// Gas consumed by the span and its descendants.
+    TotalGas,
+}
+
+impl Display for GasMeasure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GasMeasure::SelfGas => write!(f, "self gas"),
+            GasMeasure::TotalGas => write!(f, "total gas"),
+        }
+    }
+}
+
+/// A bound on an amount of gas, summing the dimensions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GasBound {
+    AtMost(u64),
+    Below(u64),
+    AtLeast(u64),
+    Above(u64),
+    Exactly(u64),
+}
+
+impl GasBound {
+    pub fn admits(&self, gas: u64) -> bool {
+        match *self {
+            GasBound::AtMost(bound) => gas <= bound,
+            GasBound::Below(bound) => gas < bound,
+            GasBound::AtLeast(bound) => gas >= bound,
+            GasBound::Above(bound) => gas > bound,
+            GasBound::Exactly(bound) => gas == bound,
+        }
+    }
+}
+
+impl Display for GasBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (op, bound) = match *self {
+            GasBound::AtMost(bound) => ("<=", bound),
+            GasBound::Below(bound) => ("<", bound),
+            GasBound::AtLeast(bound) => (">=", bound),
+            GasBound::Above(bound) => (">", bound),
+            GasBound::Exactly(bound) => ("==", bound),
+        };
+        write!(f, "{} {}", op, bound.to_formatted_string(&Locale::en))
+    }
+}
+
+/// A failed gas assertion, which displays a report of the spans out of bounds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GasAssertionError {
+    pub selector: SpanSelector,
+    pub measure: GasMeasure,
+    pub bound: GasBound,
+    /// A report of each span out of bounds, or empty if no spans matched.
+    pub report: String,
+}
+
+impl Display for GasAssertionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.report.is_empty() {
+            return write!(f, "gas assertion failed: no {} to check", self.selector);
+        }
+        write!(
+            f,
+            "gas assertion failed: {} of {} {}\n{}",
+            self.measure, self.selector, self.bound, self.report
+        )
+    }
+}
+
+impl std::error::Error for GasAssertionError {}
+
+/// Assertions on the gas consumed by spans, for enforcing gas budgets in tests.
+/// See also the `assert_span_gas!` macro.
+impl TraceAnalysis {
+    /// Checks that the gas of every selected span is within a bound.
+    /// Fails if no spans are selected, so that a mistyped label is not vacuously in bounds.
+    pub fn check_span_gas(
+        &self,
+        selector: impl Into<SpanSelector>,
+        measure: GasMeasure,
+        bound: GasBound,
+    ) -> Result<(), GasAssertionError> {
+        let selector = selector.into();
+        let selected = self.spans().iter().filter(|span| selector.matches(span)).collect_vec();
+        let failures = selected
+            .iter()
+            .filter(|span| !bound.admits(self.measure_gas(span, measure).total()))
+            .map(|span| self.format_failure(span, measure, bound))
+            .collect_vec();
+        if !selected.is_empty() && failures.is_empty() {
+            return Ok(());
+        }
+        Err(GasAssertionError { selector, measure, bound, report: failures.join("\n") })
+    }
+
+    /// Asserts that the gas of every selected span is within a bound, panicking with a report
+    /// of the spans out of bounds otherwise.
+    pub fn assert_span_gas(
+        &self,
+        selector: impl Into<SpanSelector>,
+        measure: GasMeasure,
+        bound: GasBound,
+    ) {
+        if let Err(e) = self.check_span_gas(selector, measure, bound) {
+            panic!("{}", e);
+        }
+    }
+
+    fn measure_gas(&self, span: &Span, measure: GasMeasure) -> GasCharge {
+        match measure {
+            GasMeasure::SelfGas => span.self_gas(),
+            GasMeasure::TotalGas => self.total_gas(span),
+        }
+    }
+
+    // Reports a span out of bounds, with its path, its charges, and those of its children.
+    fn format_failure(&self, span: &Span, measure: GasMeasure, bound: GasBound) -> String {
+        let gas = self.measure_gas(span, measure).total();
+        let mut lines = vec![
+            format!(
+                "  {}: {} {} is not {}",
+                span.id(),
+                measure,
+                gas.to_formatted_string(&Locale::en),
+                bound
+            ),
+            format!("    path: {}", self.path(span).iter().map(|s| s.name()).join(" > ")),
+        ];
+        let charges = match measure {
+            GasMeasure::SelfGas => span.self_gas_by_name().clone(),
+            GasMeasure::TotalGas => self.total_gas_by_name(span),
+        };
+        let sum = self.measure_gas(span, measure);
+        lines.push(format!("    charges: {}", format_gas_bits(sum, &charges, GasFormat::Total)));
+        for child in self.children(span) {
+            let total = self.total_gas(child).total().to_formatted_string(&Locale::en);
+            lines.push(format!("    child {}: total gas {}", child.id(), total));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Asserts that the gas of spans is within a bound, panicking with a report of the spans
+/// out of bounds otherwise.
+/// The first argument is a `TraceAnalysis` or an `ExecutionWrangler`, whose last message is
+/// analysed. Spans are selected by a label, or any `SpanSelector`. The bound is on total gas,
+/// or on self gas if prefixed by `self`, with any of `<=`, `<`, `>=`, `>` and `==`, e.g.
+/// ```ignore
+/// assert_span_gas!(analysis, "verify_deals", <= 2_500_000);
+/// assert_span_gas!(analysis, SpanSelector::call(MARKET_ID, 4), self < 100_000);
+/// assert_span_gas!(wrangler, SpanSelector::Message, <= 10_000_000);
+/// ```
+#[macro_export]
+macro_rules! assert_span_gas {
+    ($target:expr, $spans:expr, self $($bound:tt)+) => {
+        $target.assert_span_gas(
+            $spans,
+            $crate::analysis::assertion::GasMeasure::SelfGas,
+            $crate::__gas_bound!($($bound)+),
+        )
+    };
+    ($target:expr, $spans:expr, $($bound:tt)+) => {
+        $target.assert_span_gas(
+            $spans,
+            $crate::analysis::assertion::GasMeasure::TotalGas,
+            $crate::__gas_bound!($($bound)+),
+        )
+    };
+}
+
+// Converts a comparison, like `<= 2_500_000`, to a GasBound.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __gas_bound {
+    (<= $gas:expr) => {
+        $crate::analysis::assertion::GasBound::AtMost($gas)
+    };
+    (< $gas:expr) => {
+        $crate::analysis::assertion::GasBound::Below($gas)
+    };
+    (>= $gas:expr) => {
+        $crate::analysis::assertion::GasBound::AtLeast($gas)
+    };
+    (> $gas:expr) => {
+        $crate::analysis::assertion::GasBound::Above($gas)
+    };
+    (== $gas:expr) => {
+        $crate::analysis::assertion::GasBound::Exactly($gas)
+    };
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use cid::Cid;
+use itertools::Itertools;
+use num_format::{Locale, ToFormattedString};
+
+use crate::analysis::GasCharge;
+use crate::names::NameResolver;
+use crate::trace::{ExecutionEvent, ExecutionTrace};
+
+/// Gas attributed to the actor code that was running when it was charged.
+/// Gas charged by a call is attributed to the receiver's code, or to the caller's code if the
+/// receiver wasn't invoked (e.g. for a plain value transfer). Gas charged outside any call
+/// (e.g. for message inclusion) is unattributed.
+/// Attributions from many messages can be merged to summarise a whole workflow.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ActorGasAttribution {
+    by_code: HashMap<Cid, GasCharge>,
+    unattributed: GasCharge,
+}
+
+impl ActorGasAttribution {
+    /// Attributes the gas charged in an execution trace.
+    pub fn from_trace(trace: &ExecutionTrace) -> Self {
+        let mut attribution = Self::default();
+        let calls = trace.calls();
+        // The code running in each call. Callers precede their calls.
+        let mut running: Vec<Option<Cid>> = Vec::with_capacity(calls.len());
+        for call in &calls {
+            let code = call.code.or_else(|| call.parent.and_then(|parent| running[parent]));
+            running.push(code);
+            if call.self_gas == GasCharge::zero() {
+                continue;
+            }
+            match code {
+                Some(code) => *attribution.by_code.entry(code).or_default() += call.self_gas,
+                None => attribution.unattributed += call.self_gas,
+            }
+        }
+        // The events between each top-level call and its return.
+        let in_calls: Vec<_> = calls
+            .iter()
+            .filter(|c| c.depth == 0)
+            .map(|c| c.call_event..c.return_event.unwrap_or(usize::MAX))
+            .collect();
+        for (idx, event) in trace.events().iter().enumerate() {
+            if let ExecutionEvent::GasCharge { compute_milli, other_milli, .. } = event {
+                if !in_calls.iter().any(|range| range.contains(&idx)) {
+                    attribution.unattributed += GasCharge::new_millis(*compute_milli, *other_milli);
+                }
+            }
+        }
+        attribution
+    }
+
+    /// Attributes the gas charged across many execution traces.
+    pub fn from_traces<'a>(traces: impl IntoIterator<Item = &'a ExecutionTrace>) -> Self {
+        let mut attribution = Self::default();
+        for trace in traces {
+            attribution.merge(&Self::from_trace(trace));
+        }
+        attribution
+    }
+
+    /// Adds another attribution's gas to this one.
+    pub fn merge(&mut self, other: &ActorGasAttribution) {
+        for (code, charge) in &other.by_code {
+            *self.by_code.entry(*code).or_default() += *charge;
+        }
+        self.unattributed += other.unattributed;
+    }
+
+    /// Returns the gas attributed to each actor code.
+    pub fn by_code(&self) -> &HashMap<Cid, GasCharge> {
+        &self.by_code
+    }
+
+    /// Returns the gas charged outside any invoked actor code.
+    pub fn unattributed(&self) -> GasCharge {
+        self.unattributed
+    }
+
+    /// Returns the total gas, attributed or not.
+    pub fn total(&self) -> GasCharge {
+        self.by_code.values().fold(self.unattributed, |acc, c| acc + *c)
+    }
+
+    /// Returns the gas attributed to each actor type, in descending order of gas.
+    /// Code of an unknown type is identified by its CID.
+    pub fn by_type(&self, resolver: &NameResolver) -> Vec<(String, GasCharge)> {
+        let mut by_type: HashMap<String, GasCharge> = HashMap::new();
+        for (code, charge) in &self.by_code {
+            let name = resolver.code_type(code).map_or_else(|| code.to_string(), str::to_string);
+            *by_type.entry(name).or_default() += *charge;
+        }
+        by_type
+            .into_iter()
+            .sorted_by(|a, b| b.1.total_milli().cmp(&a.1.total_milli()).then(a.0.cmp(&b.0)))
+            .collect()
+    }
+
+    /// Formats the share of total gas attributed to each actor type, like
+    /// "Miner: 61.0%, Market: 22.0%, Power: 9.0%, (unattributed): 8.0%".
+    pub fn format_shares(&self, resolver: &NameResolver) -> String {
+        self.rows(resolver)
+            .iter()
+            .map(|(name, charge)| format!("{}: {}", name, self.share(charge)))
+            .join(", ")
+    }
+
+    /// Formats the gas attributed to each actor type, one per line, with its compute and other
+    /// dimensions and share of the total.
+    pub fn format_report(&self, resolver: &NameResolver) -> String {
+        self.rows(resolver)
+            .iter()
+            .map(|(name, charge)| {
+                format!(
+                    "{}: {} ({}, compute={}, other={})",
+                    name,
+                    charge.total().to_formatted_string(&Locale::en),
+                    self.share(charge),
+                    charge.compute().to_formatted_string(&Locale::en),
+                    charge.other().to_formatted_string(&Locale::en),
+                )
+            })
+            .join("\n")
+    }
+
+    // Gas by actor type, followed by unattributed gas if any.
+    fn rows(&self, resolver: &NameResolver) -> Vec<(String, GasCharge)> {
+        let mut rows = self.by_type(resolver);
+        if self.unattributed != GasCharge::zero() {
+            rows.push(("(unattributed)".to_string(), self.unattributed));
+        }
+        rows
+    }
+
+    fn share(&self, charge: &GasCharge) -> String {
+        let total = self.total().total_milli();
+        if total == 0 {
+            return "0.0%".to_string();
+        }
+        format!("{:.1}%", charge.total_milli() as f64 * 100.0 / total as f64)
+    }
+}
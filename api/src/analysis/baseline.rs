@@ -0,0 +1,363 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::path::Path;
+
+use anyhow::Context;
+use itertools::Itertools;
+use num_format::{Locale, ToFormattedString};
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::TraceAnalysis;
+
+/// The environment variable which, if set, makes `GasBaseline::check_saved` record
+/// baselines rather than compare against them.
+pub const UPDATE_BASELINES_VAR: &str = "UPDATE_GAS_BASELINES";
+
+/// A record of the gas consumed by each span of an analysis, which can be saved and later
+/// compared with a fresh analysis of the same scenario to detect gas regressions.
+/// Spans are keyed by their path of names from the root, e.g. `Root;Call(100->f06::4);Span(x)`.
+/// Where the same path occurs more than once, later occurrences are suffixed `#2`, `#3` etc.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasBaseline {
+    pub spans: BTreeMap<String, SpanBaseline>,
+}
+
+/// The gas consumed by a single span, in milligas.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpanBaseline {
+    pub self_milli: u64,
+    pub total_milli: u64,
+    /// Total gas by charge name.
+    pub charges: BTreeMap<String, u64>,
+}
+
+impl GasBaseline {
+    /// Records the gas consumed by each span of an analysis.
+    pub fn from_analysis(analysis: &TraceAnalysis) -> Self {
+        let mut spans = BTreeMap::new();
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+        for span in analysis.spans() {
+            let path = analysis.path(span).iter().map(|s| s.name()).join(";");
+            let n = occurrences.entry(path.clone()).or_insert(0);
+            *n += 1;
+            let key = if *n == 1 { path } else { format!("{}#{}", path, n) };
+            let charges = analysis
+                .total_gas_by_name(span)
+                .into_iter()
+                .map(|(name, c)| (name, c.total_milli()))
+                .collect();
+            spans.insert(
+                key,
+                SpanBaseline {
+                    self_milli: span.self_gas().total_milli(),
+                    total_milli: analysis.total_gas(span).total_milli(),
+                    charges,
+                },
+            );
+        }
+        Self { spans }
+    }
+
+    /// Loads a baseline from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read baseline {}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    /// Saves this baseline to a JSON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_json()?)
+            .with_context(|| format!("failed to write baseline {}", path.display()))
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        serde_json::from_str(json).context("failed to parse baseline")
+    }
+
+    /// Serializes this baseline as JSON, with spans in a stable order.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        serde_json::to_string_pretty(self).context("failed to serialize baseline")
+    }
+
+    /// Compares an analysis against the baseline saved at a path, as a guardrail in tests.
+    /// If the `UPDATE_GAS_BASELINES` environment variable is set, saves the analysis as the
+    /// baseline instead, and reports it unchanged.
+    /// Fails if there is no saved baseline and the variable isn't set, so that a missing
+    /// baseline file doesn't silently pass.
+    pub fn check_saved(
+        analysis: &TraceAnalysis,
+        path: impl AsRef<Path>,
+        options: &CompareOptions,
+    ) -> anyhow::Result<GasComparison> {
+        let path = path.as_ref();
+        if std::env::var_os(UPDATE_BASELINES_VAR).is_none() {
+            if !path.exists() {
+                anyhow::bail!(
+                    "no baseline at {}, set {} to record it",
+                    path.display(),
+                    UPDATE_BASELINES_VAR
+                );
+            }
+            return Ok(Self::load(path)?.compare(analysis, options));
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let baseline = Self::from_analysis(analysis);
+        baseline.save(path)?;
+        Ok(baseline.compare(analysis, options))
+    }
+
+    /// Compares a fresh analysis against this baseline.
+    /// The total gas of each span, and of each charge name within it, is compared against
+    /// the span's thresholds.
+    pub fn compare(&self, analysis: &TraceAnalysis, options: &CompareOptions) -> GasComparison {
+        let current = GasBaseline::from_analysis(analysis);
+        let mut comparison = GasComparison::default();
+        for (key, base) in &self.spans {
+            match current.spans.get(key) {
+                Some(cur) => {
+                    let thresholds = options.thresholds_for(key);
+                    let charges = base
+                        .charges
+                        .keys()
+                        .chain(cur.charges.keys())
+                        .unique()
+                        .sorted()
+                        .map(|name| {
+                            let baseline = base.charges.get(name).copied().unwrap_or_default();
+                            let current = cur.charges.get(name).copied().unwrap_or_default();
+                            ChargeDelta {
+                                name: name.clone(),
+                                baseline,
+                                current,
+                                regression: thresholds.exceeded(baseline, current),
+                            }
+                        })
+                        .collect_vec();
+                    let regression = thresholds.exceeded(base.total_milli, cur.total_milli)
+                        || charges.iter().any(|c| c.regression);
+                    comparison.deltas.push(SpanDelta {
+                        key: key.clone(),
+                        baseline_self: base.self_milli,
+                        current_self: cur.self_milli,
+                        baseline_total: base.total_milli,
+                        current_total: cur.total_milli,
+                        charges,
+                        regression,
+                    })
+                }
+                None => comparison.removed.push(key.clone()),
+            }
+        }
+        comparison.added =
+            current.spans.keys().filter(|k| !self.spans.contains_key(*k)).cloned().collect();
+        comparison
+    }
+}
+
+/// Limits on the increase in a span's total gas, or in that of a charge name within the span,
+/// before it is considered a regression.
+/// An increase is a regression only if it exceeds both the absolute and relative limits.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Thresholds {
+    /// Increase in milligas.
+    pub absolute_milli: u64,
+    /// Increase as a fraction of the baseline, e.g. 0.05 for 5%.
+    pub relative: f64,
+}
+
+impl Thresholds {
+    /// Returns whether the change from a baseline to a current amount exceeds these thresholds.
+    pub fn exceeded(&self, baseline: u64, current: u64) -> bool {
+        let increase = current.saturating_sub(baseline);
+        increase > self.absolute_milli
+            && (baseline == 0 || increase as f64 / baseline as f64 > self.relative)
+    }
+}
+
+impl Default for Thresholds {
+    /// Any increase is a regression.
+    fn default() -> Self {
+        Self { absolute_milli: 0, relative: 0.0 }
+    }
+}
+
+/// Options for comparing an analysis against a baseline.
+#[derive(Clone, Debug, Default)]
+pub struct CompareOptions {
+    /// Thresholds for spans that have no specific override.
+    pub default: Thresholds,
+    /// Thresholds for spans whose key contains some pattern, e.g. "Span(load_deals)".
+    /// The first matching pattern applies.
+    pub overrides: Vec<(String, Thresholds)>,
+}
+
+impl CompareOptions {
+    pub fn new(default: Thresholds) -> Self {
+        Self { default, overrides: Vec::new() }
+    }
+
+    /// Adds thresholds for spans whose key contains a pattern.
+    pub fn with_override(mut self, pattern: &str, thresholds: Thresholds) -> Self {
+        self.overrides.push((pattern.to_string(), thresholds));
+        self
+    }
+
+    fn thresholds_for(&self, key: &str) -> Thresholds {
+        self.overrides
+            .iter()
+            .find(|(pattern, _)| key.contains(pattern.as_str()))
+            .map_or(self.default, |(_, t)| *t)
+    }
+}
+
+/// The result of comparing an analysis against a baseline.
+#[derive(Clone, Debug, Default)]
+pub struct GasComparison {
+    /// Spans present in both, in baseline key order.
+    pub deltas: Vec<SpanDelta>,
+    /// Keys of spans in the analysis but not the baseline.
+    pub added: Vec<String>,
+    /// Keys of spans in the baseline but not the analysis.
+    pub removed: Vec<String>,
+}
+
+/// The change in gas consumed by a span, in milligas.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpanDelta {
+    pub key: String,
+    pub baseline_self: u64,
+    pub current_self: u64,
+    pub baseline_total: u64,
+    pub current_total: u64,
+    /// The total gas of each charge name in either the baseline or the analysis, by name.
+    pub charges: Vec<ChargeDelta>,
+    /// Whether the change in total gas, or in that of any charge name, exceeded the thresholds.
+    pub regression: bool,
+}
+
+/// The change in total gas of a charge name within a span, in milligas.
+/// A charge name absent from the baseline or the analysis has zero gas there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChargeDelta {
+    pub name: String,
+    pub baseline: u64,
+    pub current: u64,
+    /// Whether the change exceeded the span's thresholds.
+    pub regression: bool,
+}
+
+impl SpanDelta {
+    /// Returns the change in total gas.
+    pub fn total_change(&self) -> i128 {
+        self.current_total as i128 - self.baseline_total as i128
+    }
+
+    /// Returns whether the gas of the span or of any of its charge names changed.
+    pub fn is_changed(&self) -> bool {
+        self.baseline_self != self.current_self
+            || self.baseline_total != self.current_total
+            || self.changed_charges().next().is_some()
+    }
+
+    /// Returns the charge names whose gas changed.
+    pub fn changed_charges(&self) -> impl Iterator<Item = &ChargeDelta> {
+        self.charges.iter().filter(|c| c.baseline != c.current)
+    }
+}
+
+impl GasComparison {
+    pub fn has_regressions(&self) -> bool {
+        self.deltas.iter().any(|d| d.regression)
+    }
+
+    pub fn regressions(&self) -> impl Iterator<Item = &SpanDelta> {
+        self.deltas.iter().filter(|d| d.regression)
+    }
+
+    /// Returns whether the analysis has exactly the same spans and gas as the baseline.
+    pub fn is_unchanged(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.deltas.iter().all(|d| !d.is_changed())
+    }
+
+    /// Formats a report of regressions, other changed spans, and added or removed spans.
+    /// Each changed span is followed by its changed charge names, those exceeding the
+    /// thresholds marked `!`. Amounts are milligas.
+    pub fn format_report(&self) -> String {
+        let mut lines = Vec::new();
+        let regressions = self.regressions().collect_vec();
+        lines.push(format!("Gas regressions: {}", regressions.len()));
+        for delta in regressions {
+            push_delta(&mut lines, delta);
+        }
+        let changed = self.deltas.iter().filter(|d| !d.regression && d.is_changed()).collect_vec();
+        if !changed.is_empty() {
+            lines.push(format!("Other changes: {}", changed.len()));
+            for delta in changed {
+                push_delta(&mut lines, delta);
+            }
+        }
+        if !self.added.is_empty() {
+            lines.push(format!("Added spans: {}", self.added.len()));
+            lines.extend(self.added.iter().map(|k| format!("  {}", k)));
+        }
+        if !self.removed.is_empty() {
+            lines.push(format!("Removed spans: {}", self.removed.len()));
+            lines.extend(self.removed.iter().map(|k| format!("  {}", k)));
+        }
+        lines.join("\n")
+    }
+}
+
+// Appends a changed span and its changed charge names to a report.
+fn push_delta(lines: &mut Vec<String>, delta: &SpanDelta) {
+    lines.push(format!("  {}", delta));
+    for charge in delta.changed_charges() {
+        lines.push(format!("    {}{}", if charge.regression { "! " } else { "" }, charge));
+    }
+}
+
+impl Display for SpanDelta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: total {}, self {} -> {}",
+            self.key,
+            format_change(self.baseline_total, self.current_total),
+            self.baseline_self.to_formatted_string(&Locale::en),
+            self.current_self.to_formatted_string(&Locale::en),
+        )
+    }
+}
+
+impl Display for ChargeDelta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, format_change(self.baseline, self.current))
+    }
+}
+
+// Formats a change in gas with its absolute and relative amounts,
+// e.g. "1,000 -> 1,100 (+100, +10.00%)".
+fn format_change(baseline: u64, current: u64) -> String {
+    let change = current as i128 - baseline as i128;
+    let relative = if baseline == 0 {
+        "new".to_string()
+    } else {
+        format!("{:+.2}%", change as f64 * 100.0 / baseline as f64)
+    };
+    format!(
+        "{} -> {} ({}{}, {})",
+        baseline.to_formatted_string(&Locale::en),
+        current.to_formatted_string(&Locale::en),
+        if change >= 0 { "+" } else { "-" },
+        change.unsigned_abs().to_formatted_string(&Locale::en),
+        relative
+    )
+}
@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use crate::analysis::{format_gas_bits, GasCharge, GasFormat, Span, TraceAnalysis};
+
+/// A category of gas charges, grouping the FVM's many charge names by what they pay for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GasCategory {
+    /// Wasm execution and syscall overhead.
+    Execution,
+    /// Opening, reading and inspecting IPLD blocks.
+    IpldRead,
+    /// Creating and linking IPLD blocks, and updating actor state roots.
+    IpldWrite,
+    /// Message inclusion, method invocation, value transfer, actor creation and deletion,
+    /// and actor code installation.
+    Send,
+    /// Reading the chain, message and actor context, e.g. randomness, balances, and address
+    /// and code lookups.
+    Context,
+    /// Signature verification, hashing, and proof verification.
+    Crypto,
+    /// Emitting actor events.
+    Events,
+    /// Wasm memory allocation.
+    Memory,
+    /// Charges not mapped to any other category.
+    Other,
+}
+
+impl Display for GasCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            GasCategory::Execution => "execution",
+            GasCategory::IpldRead => "ipld_read",
+            GasCategory::IpldWrite => "ipld_write",
+            GasCategory::Send => "send",
+            GasCategory::Context => "context",
+            GasCategory::Crypto => "crypto",
+            GasCategory::Events => "events",
+            GasCategory::Memory => "memory",
+            GasCategory::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A mapping of gas charge names to categories.
+/// The default mapping covers the FVM price list's charge names. Overrides take precedence,
+/// to categorise charge names added by newer FVM versions or to regroup existing ones.
+#[derive(Clone, Debug, Default)]
+pub struct GasCategories {
+    overrides: HashMap<String, GasCategory>,
+}
+
+impl GasCategories {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Maps a charge name to a category, overriding the default mapping.
+    pub fn with_override(mut self, charge_name: &str, category: GasCategory) -> Self {
+        self.overrides.insert(charge_name.to_string(), category);
+        self
+    }
+
+    /// Returns the category of a charge name.
+    pub fn category(&self, charge_name: &str) -> GasCategory {
+        self.overrides.get(charge_name).copied().unwrap_or_else(|| default_category(charge_name))
+    }
+
+    /// Sums gas charges by category.
+    pub fn categorise(
+        &self,
+        charges: &HashMap<String, GasCharge>,
+    ) -> BTreeMap<GasCategory, GasCharge> {
+        let mut totals: BTreeMap<GasCategory, GasCharge> = BTreeMap::new();
+        for (name, charge) in charges {
+            *totals.entry(self.category(name)).or_default() += *charge;
+        }
+        totals
+    }
+}
+
+/// Returns the default category of a charge name.
+/// Names not charged by the FVM 4 price list are categorised as Other, and can be mapped
+/// with an override.
+pub fn default_category(charge_name: &str) -> GasCategory {
+    match charge_name {
+        "wasm_exec" | "OnSyscall" => GasCategory::Execution,
+        "OnBlockOpenBase" | "OnBlockOpen" | "OnBlockRead" | "OnBlockStat" | "OnGetRoot" => {
+            GasCategory::IpldRead
+        }
+        "OnBlockCreate" | "OnBlockLink" | "OnSetRoot" | "OnActorUpdate" => GasCategory::IpldWrite,
+        "OnChainMessage" | "OnChainReturnValue" | "OnMethodInvocation" | "OnValueTransfer"
+        | "OnCreateActor" | "OnDeleteActor" | "OnInstallActor" => GasCategory::Send,
+        "OnResolveAddress"
+        | "OnLookupAddress"
+        | "OnActorLookup"
+        | "OnGetActorCodeCid"
+        | "OnGetBuiltinActorType"
+        | "OnGetCodeCidForType"
+        | "OnSelfBalance"
+        | "OnBalanceOf"
+        | "OnNetworkContext"
+        | "OnMessageContext"
+        | "OnGetRandomness"
+        | "OnTipsetCid" => GasCategory::Context,
+        "OnRecoverSecpPublicKey"
+        | "OnHashing"
+        | "OnComputeUnsealedSectorCid"
+        | "OnVerifySignature"
+        | "OnVerifySeal"
+        | "OnVerifyAggregateSeals"
+        | "OnVerifyReplicaUpdate"
+        | "OnVerifyPost"
+        | "OnVerifyConsensusFault" => GasCategory::Crypto,
+        "OnActorEvent" => GasCategory::Events,
+        "OnMemoryGrow" => GasCategory::Memory,
+        _ => GasCategory::Other,
+    }
+}
+
+impl TraceAnalysis {
+    /// Returns the gas consumed directly by a span, excluding by its children, by category.
+    pub fn self_gas_by_category(
+        &self,
+        span: &Span,
+        categories: &GasCategories,
+    ) -> BTreeMap<GasCategory, GasCharge> {
+        categories.categorise(span.self_gas_by_name())
+    }
+
+    /// Returns the total gas consumed by a span, including by its descendants, by category.
+    pub fn total_gas_by_category(
+        &self,
+        span: &Span,
+        categories: &GasCategories,
+    ) -> BTreeMap<GasCategory, GasCharge> {
+        categories.categorise(&self.total_gas_by_name(span))
+    }
+
+    /// Formats all spans with their self and total gas subtotalled by category.
+    pub fn format_spans_by_category(
+        &self,
+        categories: &GasCategories,
+        format: GasFormat,
+    ) -> String {
+        self.spans().iter().map(|s| self.format_span_by_category(s, categories, format)).join("\n")
+    }
+
+    /// Formats a single span with its self and total gas subtotalled by category.
+    pub fn format_span_by_category(
+        &self,
+        span: &Span,
+        categories: &GasCategories,
+        format: GasFormat,
+    ) -> String {
+        let by_name = |totals: BTreeMap<GasCategory, GasCharge>| {
+            totals.into_iter().map(|(c, charge)| (c.to_string(), charge)).collect()
+        };
+        format!(
+            "Span[{}, self: {{{}}}, total: {{{}}}]",
+            span.id(),
+            format_gas_bits(
+                span.self_gas(),
+                &by_name(self.self_gas_by_category(span, categories)),
+                format
+            ),
+            format_gas_bits(
+                self.total_gas(span),
+                &by_name(self.total_gas_by_category(span, categories)),
+                format
+            ),
+        )
+    }
+}
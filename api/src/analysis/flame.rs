@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use serde_json::{json, Value};
+
+use crate::analysis::TraceAnalysis;
+
+/// Exports of a trace analysis for flame graph tools.
+/// Each span's self gas is attributed to the stack of spans from the root to that span,
+/// with the charge name as the leaf frame. Stacks are weighted by milligas.
+impl TraceAnalysis {
+    /// Formats the analysis as folded stacks, one line per distinct stack, e.g.
+    /// `Root;Call(100->f06::4);Span(load_deals);ipld_get 12345`.
+    /// This is the input format of inferno and the original flamegraph.pl.
+    pub fn format_folded_stacks(&self) -> String {
+        self.folded_stacks()
+            .iter()
+            .map(|(frames, milli)| format!("{} {}", frames.join(";"), milli))
+            .join("\n")
+    }
+
+    /// Exports the analysis as a speedscope (https://www.speedscope.app) sampled profile,
+    /// with one sample per distinct stack.
+    pub fn to_speedscope(&self, name: &str) -> Value {
+        let mut frames: Vec<String> = Vec::new();
+        let mut frame_indices: HashMap<String, usize> = HashMap::new();
+        let mut samples = Vec::new();
+        let mut weights = Vec::new();
+        for (stack, milli) in self.folded_stacks() {
+            let sample = stack
+                .into_iter()
+                .map(|frame| {
+                    *frame_indices.entry(frame.clone()).or_insert_with(|| {
+                        frames.push(frame);
+                        frames.len() - 1
+                    })
+                })
+                .collect_vec();
+            samples.push(sample);
+            weights.push(milli);
+        }
+        let total: u64 = weights.iter().sum();
+
+        json!({
+            "$schema": "https://www.speedscope.app/file-format-schema.json",
+            "name": name,
+            "exporter": "fvm-workbench",
+            "activeProfileIndex": 0,
+            "shared": {
+                "frames": frames.iter().map(|f| json!({ "name": f })).collect_vec(),
+            },
+            "profiles": [{
+                "type": "sampled",
+                "name": name,
+                "unit": "none",
+                "startValue": 0,
+                "endValue": total,
+                "samples": samples,
+                "weights": weights,
+            }],
+        })
+    }
+
+    /// Collects the distinct stacks of frame names and the milligas charged to each,
+    /// in the order spans were opened.
+    fn folded_stacks(&self) -> Vec<(Vec<String>, u64)> {
+        let mut stacks: Vec<(Vec<String>, u64)> = Vec::new();
+        let mut stack_indices: HashMap<Vec<String>, usize> = HashMap::new();
+        for span in self.spans() {
+            let path = self.path(span).iter().map(|s| frame_name(&s.name())).collect_vec();
+            let charges = span.self_gas_by_name().iter().sorted_by(|a, b| a.0.cmp(b.0));
+            for (name, charge) in charges {
+                let mut frames = path.clone();
+                frames.push(frame_name(name));
+                let milli = charge.total_milli();
+                match stack_indices.get(&frames) {
+                    Some(idx) => stacks[*idx].1 += milli,
+                    None => {
+                        stack_indices.insert(frames.clone(), stacks.len());
+                        stacks.push((frames, milli));
+                    }
+                }
+            }
+        }
+        stacks
+    }
+}
+
+// Sanitises a frame name for the folded format, in which ';' separates frames.
+fn frame_name(name: &str) -> String {
+    name.replace(';', ",")
+}
@@ -0,0 +1,134 @@
+use std::cmp::Reverse;
+
+use itertools::Itertools;
+use num_format::{Locale, ToFormattedString};
+
+use crate::analysis::{GasCharge, Span, SpanKind, TraceAnalysis};
+
+/// The gas of one charge name made directly by one span.
+#[derive(Clone, Debug)]
+pub struct HotCharge<'a> {
+    pub span: &'a Span,
+    pub name: &'a str,
+    pub gas: GasCharge,
+}
+
+/// Queries for where a message's gas goes, to decide what to optimise first.
+impl TraceAnalysis {
+    /// Returns the charges of most gas, up to some number, each being the total of one charge
+    /// name made directly by one span.
+    pub fn hot_charges(&self, n: usize) -> Vec<HotCharge<'_>> {
+        self.spans()
+            .iter()
+            .flat_map(|span| {
+                span.self_gas_by_name().iter().map(move |(name, gas)| HotCharge {
+                    span,
+                    name: name.as_str(),
+                    gas: *gas,
+                })
+            })
+            .sorted_by_key(|c| (Reverse(c.gas.total_milli()), c.span.index(), c.name))
+            .take(n)
+            .collect()
+    }
+
+    /// Returns the call spans of most total gas, including by their descendants, up to some
+    /// number. A call's subtree includes those of the calls it makes, so nested calls may
+    /// all rank highly.
+    pub fn hot_calls(&self, n: usize) -> Vec<(&Span, GasCharge)> {
+        self.spans()
+            .iter()
+            .filter(|span| span.kind() == SpanKind::Call)
+            .map(|span| (span, self.total_gas(span)))
+            .sorted_by_key(|(span, gas)| (Reverse(gas.total_milli()), span.index()))
+            .take(n)
+            .collect()
+    }
+
+    /// Returns the charge names of most gas over the whole message, up to some number.
+    pub fn hot_charge_names(&self, n: usize) -> Vec<(String, GasCharge)> {
+        self.total_gas_by_name(self.root())
+            .into_iter()
+            .sorted_by(|a, b| b.1.total_milli().cmp(&a.1.total_milli()).then(a.0.cmp(&b.0)))
+            .take(n)
+            .collect()
+    }
+
+    /// Returns the most expensive path from the root to a leaf span, following at each span
+    /// the child of most total gas. Where children tie, the first opened is followed.
+    pub fn critical_path(&self) -> Vec<&Span> {
+        let mut path = vec![self.root()];
+        let mut span = self.root();
+        while let Some(child) =
+            self.children(span).min_by_key(|child| Reverse(self.total_gas(child).total_milli()))
+        {
+            path.push(child);
+            span = child;
+        }
+        path
+    }
+
+    /// Formats a report of the critical path, the hottest calls and charges, and the
+    /// dominant charge names, listing up to some number of each, e.g.
+    /// ```text
+    /// Total gas: 8
+    /// Critical path:
+    ///   0-Root total=8 (100.0%) self=0
+    ///   1-Call(100->f06::4) total=8 (100.0%) self=2 top=wasm_exec
+    ///   3-Call(6->f07::8) total=5 (62.5%) self=1 top=wasm_exec
+    ///   4-Span(load_deals) total=4 (50.0%) self=4 top=ipld_get
+    /// Hot calls:
+    ///   1-Call(100->f06::4) total=8 (100.0%)
+    /// Hot charges:
+    ///   ipld_get=4 (50.0%) in 4-Span(load_deals)
+    /// Hot charge names:
+    ///   ipld_get=4 (50.0%)
+    /// ```
+    pub fn format_hotspots(&self, n: usize) -> String {
+        let total = self.total_gas(self.root()).total_milli();
+        let share = |gas: GasCharge| match total {
+            0 => 0.0,
+            _ => gas.total_milli() as f64 * 100.0 / total as f64,
+        };
+        let amount = |gas: GasCharge| gas.total().to_formatted_string(&Locale::en);
+
+        let mut lines = vec![format!("Total gas: {}", amount(self.total_gas(self.root())))];
+        lines.push("Critical path:".to_string());
+        for span in self.critical_path() {
+            let span_total = self.total_gas(span);
+            let top = span
+                .self_gas_by_name()
+                .iter()
+                .min_by(|a, b| b.1.total_milli().cmp(&a.1.total_milli()).then(a.0.cmp(b.0)))
+                .map(|(name, _)| format!(" top={}", name))
+                .unwrap_or_default();
+            lines.push(format!(
+                "  {} total={} ({:.1}%) self={}{}",
+                span.id(),
+                amount(span_total),
+                share(span_total),
+                amount(span.self_gas()),
+                top
+            ));
+        }
+        lines.push("Hot calls:".to_string());
+        for (span, gas) in self.hot_calls(n) {
+            lines.push(format!("  {} total={} ({:.1}%)", span.id(), amount(gas), share(gas)));
+        }
+        lines.push("Hot charges:".to_string());
+        for c in self.hot_charges(n) {
+            lines.push(format!(
+                "  {}={} ({:.1}%) in {}",
+                c.name,
+                amount(c.gas),
+                share(c.gas),
+                c.span.id()
+            ));
+        }
+        lines.push("Hot charge names:".to_string());
+        for (name, gas) in self.hot_charge_names(n) {
+            lines.push(format!("  {}={} ({:.1}%)", name, amount(gas), share(gas)));
+        }
+        lines.join("\n")
+    }
+}
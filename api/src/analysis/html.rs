@@ -0,0 +1,415 @@
+use std::fmt::Write;
+
+use itertools::Itertools;
+use num_format::{Locale, ToFormattedString};
+
+use crate::analysis::category::GasCategories;
+use crate::analysis::{milli_to_gas, GasCharge, Span, SpanKind, TraceAnalysis};
+use crate::names::NameResolver;
+use crate::trace::decode::DecoderRegistry;
+use crate::trace::invocation::Invocation;
+use crate::trace::query::CallOutcome;
+
+// Dimensions of the flame chart, in pixels.
+const FLAME_WIDTH: f64 = 1200.0;
+const FLAME_ROW: f64 = 18.0;
+// Approximate width of a character of flame chart labels, in pixels.
+const FLAME_CHAR: f64 = 7.0;
+
+/// A static HTML report of the gas consumed by one or more messages, e.g. those of a scenario.
+/// The report is a single file with no external assets, so it can be attached to a pull request
+/// and viewed in any browser. Each message has a collapsible call tree with decoded parameters
+/// and return values, a table of spans sortable by self or total gas, a breakdown by charge
+/// name, and a flame chart.
+pub struct HtmlReport {
+    title: String,
+    // The rendered section for each message, with its name.
+    sections: Vec<(String, String)>,
+}
+
+impl HtmlReport {
+    pub fn new(title: &str) -> Self {
+        Self { title: title.to_string(), sections: Vec::new() }
+    }
+
+    /// Adds a message to the report.
+    /// The call tree, if provided, is usually from the same trace as the analysis. Names are
+    /// resolved for the call tree, but the analysis' spans keep the labels they already have.
+    pub fn add_message(
+        &mut self,
+        name: &str,
+        analysis: &TraceAnalysis,
+        calls: Option<&Invocation>,
+        resolver: &NameResolver,
+        decoders: &DecoderRegistry,
+    ) {
+        let mut html = String::new();
+        let id = self.sections.len();
+        let total = analysis.total_gas(analysis.root());
+        writeln!(html, "<section id=\"message-{}\">", id).unwrap();
+        writeln!(html, "<h2>{}</h2>", escape(name)).unwrap();
+        writeln!(
+            html,
+            "<p>Total gas: {} (compute {}, other {})</p>",
+            gas(total.total_milli()),
+            gas(total.compute_milli()),
+            gas(total.other_milli())
+        )
+        .unwrap();
+        if !analysis.warnings().is_empty() {
+            writeln!(html, "<ul class=\"warnings\">").unwrap();
+            for warning in analysis.warnings() {
+                writeln!(html, "<li>{}</li>", escape(&warning.to_string())).unwrap();
+            }
+            writeln!(html, "</ul>").unwrap();
+        }
+        if let Some(calls) = calls {
+            writeln!(html, "<h3>Calls</h3>\n<div class=\"calls\">").unwrap();
+            write_invocation(&mut html, calls, resolver, decoders);
+            writeln!(html, "</div>").unwrap();
+        }
+        writeln!(html, "<h3>Flame chart</h3>").unwrap();
+        write_flame_chart(&mut html, analysis);
+        writeln!(html, "<h3>Spans</h3>").unwrap();
+        write_span_table(&mut html, analysis);
+        writeln!(html, "<h3>Charges</h3>").unwrap();
+        write_charge_table(&mut html, analysis);
+        writeln!(html, "</section>").unwrap();
+        self.sections.push((name.to_string(), html));
+    }
+
+    /// Renders the report as a complete HTML document.
+    pub fn render(&self) -> String {
+        let mut html = String::new();
+        writeln!(html, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">").unwrap();
+        writeln!(html, "<title>{}</title>", escape(&self.title)).unwrap();
+        writeln!(html, "<style>{}</style>\n</head>\n<body>", STYLE).unwrap();
+        writeln!(html, "<h1>{}</h1>", escape(&self.title)).unwrap();
+        if self.sections.len() > 1 {
+            writeln!(html, "<ol class=\"contents\">").unwrap();
+            for (id, (name, _)) in self.sections.iter().enumerate() {
+                writeln!(html, "<li><a href=\"#message-{}\">{}</a></li>", id, escape(name))
+                    .unwrap();
+            }
+            writeln!(html, "</ol>").unwrap();
+        }
+        for (_, section) in &self.sections {
+            html += section;
+        }
+        writeln!(html, "<script>{}</script>\n</body>\n</html>", SCRIPT).unwrap();
+        html
+    }
+}
+
+impl TraceAnalysis {
+    /// Renders a single-message HTML report of this analysis and its call tree.
+    /// See [`HtmlReport`] for reports of many messages.
+    pub fn to_html(
+        &self,
+        title: &str,
+        calls: Option<&Invocation>,
+        resolver: &NameResolver,
+        decoders: &DecoderRegistry,
+    ) -> String {
+        let mut report = HtmlReport::new(title);
+        report.add_message(title, self, calls, resolver, decoders);
+        report.render()
+    }
+}
+
+fn write_invocation(
+    html: &mut String,
+    inv: &Invocation,
+    resolver: &NameResolver,
+    decoders: &DecoderRegistry,
+) {
+    let call = &inv.call;
+    let type_name = match &call.code {
+        Some(code) => resolver.code_type(code),
+        None => call.to.id().ok().and_then(|id| resolver.actor_type(id)),
+    };
+    let (status_class, status) = match &call.outcome {
+        CallOutcome::Return { exit_code, .. } if exit_code.is_success() => {
+            ("ok", format!("exit={}", exit_code))
+        }
+        CallOutcome::Return { exit_code, .. } => ("fail", format!("exit={}", exit_code)),
+        CallOutcome::Error { errno, .. } => ("fail", format!("error={}", errno)),
+        CallOutcome::Unfinished => ("fail", "unfinished".to_string()),
+    };
+    writeln!(
+        html,
+        "<details open><summary><span class=\"label\">{}</span> <span class=\"{}\">{}</span> \
+         gas={} (self {})</summary>",
+        escape(&resolver.call_label(call.from, &call.to, call.method, call.code.as_ref())),
+        status_class,
+        escape(&status),
+        gas(call.gas.total_milli()),
+        gas(call.self_gas.total_milli()),
+    )
+    .unwrap();
+    writeln!(html, "<div class=\"detail\">value={}</div>", escape(&call.value.to_string()))
+        .unwrap();
+    if let Some(params) = &call.params {
+        let params = decoders.format_params(type_name, call.method, params);
+        writeln!(html, "<div class=\"detail\">params={}</div>", escape(&params)).unwrap();
+    }
+    match &call.outcome {
+        CallOutcome::Return { return_value: Some(ret), .. } => {
+            let ret = decoders.format_return(type_name, call.method, ret);
+            writeln!(html, "<div class=\"detail\">return={}</div>", escape(&ret)).unwrap();
+        }
+        CallOutcome::Error { reason, .. } => {
+            writeln!(html, "<div class=\"detail\">reason={}</div>", escape(reason)).unwrap();
+        }
+        _ => {}
+    }
+    for sub in &inv.subinvocations {
+        write_invocation(html, sub, resolver, decoders);
+    }
+    writeln!(html, "</details>").unwrap();
+}
+
+fn write_span_table(html: &mut String, analysis: &TraceAnalysis) {
+    let root_total = analysis.total_gas(analysis.root()).total_milli();
+    writeln!(
+        html,
+        "<table class=\"sortable\">\n<thead><tr><th>#</th><th>Span</th><th>Self gas</th>\
+         <th>Total gas</th><th>Self share</th><th>Total share</th></tr></thead>\n<tbody>"
+    )
+    .unwrap();
+    for span in analysis.spans() {
+        let depth = analysis.path(span).len() - 1;
+        let self_milli = span.self_gas().total_milli();
+        let total_milli = analysis.total_gas(span).total_milli();
+        writeln!(
+            html,
+            "<tr><td data-value=\"{}\">{}</td><td style=\"padding-left: {}em\">{}</td>\
+             {}{}{}{}</tr>",
+            span.index(),
+            span.index(),
+            depth + 1,
+            escape(&span.name()),
+            gas_cell(self_milli),
+            gas_cell(total_milli),
+            share_cell(self_milli, root_total),
+            share_cell(total_milli, root_total),
+        )
+        .unwrap();
+    }
+    writeln!(html, "</tbody>\n</table>").unwrap();
+}
+
+fn write_charge_table(html: &mut String, analysis: &TraceAnalysis) {
+    let categories = GasCategories::new();
+    let root_total = analysis.total_gas(analysis.root()).total_milli();
+    writeln!(
+        html,
+        "<table class=\"sortable\">\n<thead><tr><th>Charge</th><th>Category</th><th>Gas</th>\
+         <th>Compute</th><th>Other</th><th>Share</th></tr></thead>\n<tbody>"
+    )
+    .unwrap();
+    let charges = analysis
+        .total_gas_by_name(analysis.root())
+        .into_iter()
+        .sorted_by(|a, b| b.1.total_milli().cmp(&a.1.total_milli()).then(a.0.cmp(&b.0)));
+    for (name, charge) in charges {
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td>{}{}{}{}</tr>",
+            escape(&name),
+            categories.category(&name),
+            gas_cell(charge.total_milli()),
+            gas_cell(charge.compute_milli()),
+            gas_cell(charge.other_milli()),
+            share_cell(charge.total_milli(), root_total),
+        )
+        .unwrap();
+    }
+    writeln!(html, "</tbody>\n</table>").unwrap();
+}
+
+// Renders the spans as an icicle-style flame chart, with the root at the top and each span's
+// children laid out beneath it in the order they were opened, followed by its self gas
+// broken down by charge name. Widths are proportional to total gas.
+fn write_flame_chart(html: &mut String, analysis: &TraceAnalysis) {
+    let root = analysis.root();
+    let root_total = analysis.total_gas(root).total_milli();
+    let depth = analysis.spans().iter().map(|s| analysis.path(s).len()).max().unwrap_or(1) + 1;
+    writeln!(
+        html,
+        "<svg class=\"flame\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        FLAME_WIDTH,
+        depth as f64 * FLAME_ROW,
+        FLAME_WIDTH,
+        depth as f64 * FLAME_ROW
+    )
+    .unwrap();
+    if root_total > 0 {
+        let scale = FLAME_WIDTH / root_total as f64;
+        write_flame_span(html, analysis, root, 0.0, 0, scale, root_total);
+    }
+    writeln!(html, "</svg>").unwrap();
+}
+
+fn write_flame_span(
+    html: &mut String,
+    analysis: &TraceAnalysis,
+    span: &Span,
+    x: f64,
+    depth: usize,
+    scale: f64,
+    root_total: u64,
+) {
+    let total = analysis.total_gas(span);
+    let colour = match span.kind() {
+        SpanKind::Root => "hsl(0, 0%, 80%)".to_string(),
+        SpanKind::Call => format!("hsl({}, 80%, 65%)", 10 + hue(&span.name()) % 40),
+        SpanKind::Named => format!("hsl({}, 60%, 70%)", 190 + hue(&span.name()) % 50),
+    };
+    write_flame_rect(html, &span.name(), total, x, depth, scale, root_total, &colour);
+    let mut cursor = x;
+    for child in analysis.children(span) {
+        write_flame_span(html, analysis, child, cursor, depth + 1, scale, root_total);
+        cursor += analysis.total_gas(child).total_milli() as f64 * scale;
+    }
+    let charges = span
+        .self_gas_by_name()
+        .iter()
+        .sorted_by(|a, b| b.1.total_milli().cmp(&a.1.total_milli()).then(a.0.cmp(b.0)));
+    for (name, charge) in charges {
+        let colour = format!("hsl({}, 50%, 70%)", 80 + hue(name) % 60);
+        write_flame_rect(html, name, *charge, cursor, depth + 1, scale, root_total, &colour);
+        cursor += charge.total_milli() as f64 * scale;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_flame_rect(
+    html: &mut String,
+    name: &str,
+    charge: GasCharge,
+    x: f64,
+    depth: usize,
+    scale: f64,
+    root_total: u64,
+    colour: &str,
+) {
+    let width = charge.total_milli() as f64 * scale;
+    // Omit frames too narrow to see.
+    if width < 0.5 {
+        return;
+    }
+    let y = depth as f64 * FLAME_ROW;
+    writeln!(
+        html,
+        "<g><title>{}: {} gas ({})</title><rect x=\"{:.2}\" y=\"{}\" width=\"{:.2}\" \
+         height=\"{}\" fill=\"{}\"/>",
+        escape(name),
+        gas(charge.total_milli()),
+        share(charge.total_milli(), root_total),
+        x,
+        y,
+        width,
+        FLAME_ROW - 1.0,
+        colour,
+    )
+    .unwrap();
+    let chars = ((width - 6.0) / FLAME_CHAR) as usize;
+    if chars >= 3 {
+        let label: String = if name.chars().count() > chars {
+            name.chars().take(chars - 2).chain("..".chars()).collect()
+        } else {
+            name.to_string()
+        };
+        writeln!(
+            html,
+            "<text x=\"{:.2}\" y=\"{}\">{}</text>",
+            x + 3.0,
+            y + FLAME_ROW - 5.0,
+            escape(&label)
+        )
+        .unwrap();
+    }
+    writeln!(html, "</g>").unwrap();
+}
+
+// A table cell showing an amount of gas, sortable by its exact milligas.
+fn gas_cell(milli: u64) -> String {
+    format!("<td class=\"num\" data-value=\"{}\">{}</td>", milli, gas(milli))
+}
+
+fn share_cell(milli: u64, total: u64) -> String {
+    format!("<td class=\"num\" data-value=\"{}\">{}</td>", milli, share(milli, total))
+}
+
+fn gas(milli: u64) -> String {
+    milli_to_gas(milli).to_formatted_string(&Locale::en)
+}
+
+fn share(milli: u64, total: u64) -> String {
+    if total == 0 {
+        return "0.0%".to_string();
+    }
+    format!("{:.1}%", milli as f64 * 100.0 / total as f64)
+}
+
+// A stable hue offset for a name, so the same frame is the same colour in every chart.
+fn hue(name: &str) -> u32 {
+    name.bytes().fold(0u32, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u32))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; font-size: 14px; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { padding: 2px 8px; border-bottom: 1px solid #ddd; text-align: left; }
+th { cursor: pointer; background: #f4f4f4; }
+td.num { text-align: right; font-family: monospace; }
+details { margin-left: 1.5em; }
+summary { cursor: pointer; }
+.calls > details { margin-left: 0; }
+.label { font-family: monospace; font-weight: bold; }
+.detail { margin-left: 1.5em; font-family: monospace; color: #555; white-space: pre-wrap; }
+.ok { color: #2a7d2a; }
+.fail { color: #c0392b; font-weight: bold; }
+.warnings { color: #c0392b; }
+svg.flame text { font-family: monospace; font-size: 11px; pointer-events: none; }
+svg.flame rect { stroke: white; stroke-width: 0.5; }
+"#;
+
+// Sorts a table by a column when its header is clicked, descending first.
+// Cells with a data-value attribute sort numerically by it, others by their text.
+// Values are compared as BigInts, since milligas may exceed the integers a double holds exactly.
+const SCRIPT: &str = r#"
+document.querySelectorAll('table.sortable th').forEach(function (th) {
+  th.addEventListener('click', function () {
+    var body = th.closest('table').tBodies[0];
+    var col = th.cellIndex;
+    var desc = th.dataset.order !== 'desc';
+    th.dataset.order = desc ? 'desc' : 'asc';
+    var key = function (row) {
+      var cell = row.cells[col];
+      return cell.dataset.value !== undefined ? BigInt(cell.dataset.value) : cell.textContent;
+    };
+    Array.from(body.rows).sort(function (a, b) {
+      var x = key(a), y = key(b);
+      var order = x < y ? -1 : x > y ? 1 : 0;
+      return desc ? -order : order;
+    }).forEach(function (row) { body.appendChild(row); });
+  });
+});
+"#;
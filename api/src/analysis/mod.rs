@@ -0,0 +1,592 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::{Add, AddAssign};
+use std::time::Duration;
+
+use cid::Cid;
+use fvm_shared::address::Address;
+use fvm_shared::{ActorID, MethodNum};
+use itertools::Itertools;
+use num_format::{Locale, ToFormattedString};
+
+use crate::analysis::timing::ChargeTiming;
+use crate::names::NameResolver;
+use crate::trace::{ExecutionEvent, ExecutionTrace};
+
+pub mod aggregate;
+pub mod assertion;
+pub mod attribution;
+pub mod baseline;
+pub mod category;
+pub mod flame;
+pub mod hotspot;
+pub mod html;
+pub mod table;
+pub mod timing;
+
+/// Analysis of an execution trace.
+/// Analysis takes the form of a tree of spans, each summarising some section of the trace.
+/// The root span covers the whole trace. Each call (inter-actor message send) and each named
+/// span is a child of the span that was innermost when it was opened.
+/// Each gas charge is attributed to the innermost open span as "self" gas. The "total" gas of
+/// a span is its self gas plus the total gas of its children, computed by traversal.
+pub struct TraceAnalysis {
+    // All spans, in the order they were opened. The root span is at index 0.
+    spans: Vec<Span>,
+    warnings: Vec<AnalysisError>,
+}
+
+/// How an analysis treats a trace in which calls and spans are not well nested.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnalysisMode {
+    /// Any problem fails the analysis.
+    Strict,
+    /// Problems are recorded as warnings, and the analysis recovers.
+    Lenient,
+}
+
+/// A problem with the nesting of calls and spans in a trace.
+/// Event indices refer to the position in the trace's events at which the problem was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnalysisError {
+    /// A span end that doesn't match any open named span.
+    UnmatchedSpanEnd { event: usize, label: String },
+    /// A call return or error that doesn't match any open call.
+    UnmatchedCallReturn { event: usize },
+    /// A span that was still open when its enclosing call returned, or at the end of the trace.
+    UnclosedSpan { event: usize, span: String },
+    /// A span that was still open when a span enclosing it ended.
+    OverlappingSpan { event: usize, span: String, ended: String },
+}
+
+impl Display for AnalysisError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnalysisError::UnmatchedSpanEnd { event, label } => {
+                write!(f, "event {}: end of span {} that is not open", event, label)
+            }
+            AnalysisError::UnmatchedCallReturn { event } => {
+                write!(f, "event {}: return with no open call", event)
+            }
+            AnalysisError::UnclosedSpan { event, span } => {
+                write!(f, "event {}: span {} was not closed", event, span)
+            }
+            AnalysisError::OverlappingSpan { event, span, ended } => {
+                write!(f, "event {}: span {} was not closed before {} ended", event, span, ended)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AnalysisError {}
+
+impl TraceAnalysis {
+    /// Builds a new analysis from an execution trace.
+    /// Spans are inferred from (1) Calls (inter-actor message sends), and
+    /// (2) SpanStart/SpanEnd events delimiting named spans.
+    /// Returns an error if the trace's calls and spans are not well nested.
+    pub fn build(trace: ExecutionTrace) -> Result<TraceAnalysis, AnalysisError> {
+        Self::build_with_mode(trace, AnalysisMode::Strict)
+    }
+
+    /// Builds a new analysis from an execution trace, tolerating malformed span and call
+    /// sequences. Problems are recorded as warnings rather than errors: unmatched ends are
+    /// ignored, spans left open are closed when their enclosing call returns or at the
+    /// end of the trace, and spans left open when an enclosing span ends remain open.
+    pub fn build_lenient(trace: ExecutionTrace) -> TraceAnalysis {
+        Self::build_with_mode(trace, AnalysisMode::Lenient)
+            .expect("lenient analysis reports problems as warnings")
+    }
+
+    /// Builds a new analysis from an execution trace with the given tolerance of problems.
+    pub fn build_with_mode(
+        trace: ExecutionTrace,
+        mode: AnalysisMode,
+    ) -> Result<TraceAnalysis, AnalysisError> {
+        // The calls, with their receivers' code, in the order they were made.
+        let mut calls = trace.calls().into_iter();
+        let mut spans = vec![Span::new(0, SpanKind::Root, "Root".to_string(), None)];
+        // Indices of the spans that are currently open, innermost last.
+        let mut open = vec![0];
+        let mut warnings = Vec::new();
+        // Reports a problem as either an error or a warning, according to the mode.
+        let mut report = |problem: AnalysisError| match mode {
+            AnalysisMode::Strict => Err(problem),
+            AnalysisMode::Lenient => {
+                warnings.push(problem);
+                Ok(())
+            }
+        };
+        for (event_idx, event) in trace.events().iter().enumerate() {
+            match event {
+                ExecutionEvent::GasCharge { name, compute_milli, other_milli, elapsed } => {
+                    // Add gas, and time if recorded, to the innermost open span.
+                    let charge = GasCharge::new_millis(*compute_milli, *other_milli);
+                    let span = &mut spans[*open.last().unwrap()];
+                    span.add_self_gas(name.to_string(), charge);
+                    if let Some(elapsed) = elapsed {
+                        span.add_self_timing(name.to_string(), *elapsed, charge);
+                    }
+                }
+                ExecutionEvent::Call { .. } => {
+                    // Each call event has a record, in the same order.
+                    let call = calls.next().unwrap();
+                    let label = format!("Call({}->{}::{})", call.from, call.to, call.method);
+                    let idx = push_span(&mut spans, &open, SpanKind::Call, label);
+                    spans[idx].call = Some(CallInfo {
+                        from: call.from,
+                        to: call.to,
+                        method: call.method,
+                        code: call.code,
+                    });
+                    open.push(idx);
+                }
+                ExecutionEvent::CallReturn { .. } | ExecutionEvent::CallError { .. } => {
+                    let Some(call_pos) =
+                        open.iter().rposition(|s| spans[*s].kind == SpanKind::Call)
+                    else {
+                        report(AnalysisError::UnmatchedCallReturn { event: event_idx })?;
+                        continue;
+                    };
+                    // Close the innermost call span, along with any named spans opened inside it.
+                    for unclosed in open.drain(call_pos..).skip(1) {
+                        report(AnalysisError::UnclosedSpan {
+                            event: event_idx,
+                            span: spans[unclosed].id(),
+                        })?;
+                    }
+                }
+                ExecutionEvent::SpanStart { label, attrs } => {
+                    let idx = push_span(&mut spans, &open, SpanKind::Named, label.clone());
+                    spans[idx].attrs = attrs.clone();
+                    open.push(idx);
+                }
+                ExecutionEvent::SpanEnd { label } => {
+                    // Close the innermost open span with a matching label, among those opened
+                    // within the innermost open call (or the root).
+                    let call_pos =
+                        open.iter().rposition(|s| spans[*s].kind != SpanKind::Named).unwrap();
+                    let matching = open[call_pos..]
+                        .iter()
+                        .rposition(|s| {
+                            spans[*s].kind == SpanKind::Named && spans[*s].label == *label
+                        })
+                        .map(|pos| call_pos + pos);
+                    match matching {
+                        Some(pos) => {
+                            let ended = open.remove(pos);
+                            for overlapping in &open[pos..] {
+                                report(AnalysisError::OverlappingSpan {
+                                    event: event_idx,
+                                    span: spans[*overlapping].id(),
+                                    ended: spans[ended].id(),
+                                })?;
+                            }
+                        }
+                        None => report(AnalysisError::UnmatchedSpanEnd {
+                            event: event_idx,
+                            label: label.clone(),
+                        })?,
+                    }
+                }
+                ExecutionEvent::InvokeActor { .. }
+                | ExecutionEvent::Ipld { .. }
+                | ExecutionEvent::Log { .. }
+                | ExecutionEvent::ActorEvent { .. }
+                | ExecutionEvent::Unrecognized { .. } => {}
+            }
+        }
+        // Only the root span should remain open.
+        for unclosed in open.drain(1..) {
+            report(AnalysisError::UnclosedSpan {
+                event: trace.events().len(),
+                span: spans[unclosed].id(),
+            })?;
+        }
+
+        sum_totals(&mut spans);
+        Ok(TraceAnalysis { spans, warnings })
+    }
+
+    /// Relabels call spans with actor and method names, like "Call(faucet->Init::Exec)".
+    /// Labels are used to find spans, and to key baselines, so should be resolved consistently.
+    pub fn resolve_names(&mut self, resolver: &NameResolver) {
+        for span in self.spans.iter_mut() {
+            if let Some(call) = &span.call {
+                span.label =
+                    resolver.call_label(call.from, &call.to, call.method, call.code.as_ref());
+            }
+        }
+    }
+
+    /// Returns the problems tolerated when building a lenient analysis.
+    pub fn warnings(&self) -> &[AnalysisError] {
+        &self.warnings
+    }
+
+    /// Returns all spans, in the order they were opened.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Returns the root span, which covers the whole trace.
+    pub fn root(&self) -> &Span {
+        &self.spans[0]
+    }
+
+    /// Returns the span with some index, if it exists.
+    pub fn span(&self, index: usize) -> Option<&Span> {
+        self.spans.get(index)
+    }
+
+    /// Returns the parent of a span, or None for the root.
+    pub fn parent(&self, span: &Span) -> Option<&Span> {
+        span.parent.map(|idx| &self.spans[idx])
+    }
+
+    /// Returns the children of a span, in the order they were opened.
+    pub fn children<'a>(&'a self, span: &'a Span) -> impl Iterator<Item = &'a Span> + 'a {
+        span.children.iter().map(move |idx| &self.spans[*idx])
+    }
+
+    /// Returns the spans with some label, in the order they were opened.
+    /// The label of a named span is the text it was opened with, e.g. "load_deals".
+    /// The label of a call span is like "Call(100->f06::4)", or "Call(faucet->Init::Exec)"
+    /// once names are resolved.
+    pub fn find_by_label<'a>(&'a self, label: &'a str) -> impl Iterator<Item = &'a Span> + 'a {
+        self.spans.iter().filter(move |s| s.label == label)
+    }
+
+    /// Returns the total gas consumed by a span, including by its descendants.
+    pub fn total_gas(&self, span: &Span) -> GasCharge {
+        span.total_gas_sum
+    }
+
+    /// Returns the total gas consumed by a span, including by its descendants, by charge name.
+    pub fn total_gas_by_name(&self, span: &Span) -> HashMap<String, GasCharge> {
+        span.total_gas.clone()
+    }
+
+    /// Returns the path of spans from the root to a span, inclusive.
+    pub fn path<'a>(&'a self, span: &'a Span) -> Vec<&'a Span> {
+        let mut path = vec![span];
+        while let Some(parent) = self.parent(path.last().unwrap()) {
+            path.push(parent);
+        }
+        path.reverse();
+        path
+    }
+
+    /// Formats all spans, one per line, with their self and total gas.
+    /// See the `table` module for exports to CSV and Markdown.
+    pub fn format_spans(&self) -> String {
+        self.format_spans_with(GasFormat::Total)
+    }
+
+    /// Formats all spans, showing gas amounts in some format.
+    pub fn format_spans_with(&self, format: GasFormat) -> String {
+        self.spans.iter().map(|s| self.format_span_with(s, format)).join("\n")
+    }
+
+    /// Formats a single span with its self and total gas.
+    pub fn format_span(&self, span: &Span) -> String {
+        self.format_span_with(span, GasFormat::Total)
+    }
+
+    /// Formats a single span with its self and total gas, showing gas amounts in some format.
+    pub fn format_span_with(&self, span: &Span, format: GasFormat) -> String {
+        format!(
+            "Span[{}, self: {{{}}}, total: {{{}}}]",
+            span.id(),
+            format_gas_bits(span.self_gas_sum, &span.self_gas, format),
+            format_gas_bits(self.total_gas(span), &self.total_gas_by_name(span), format),
+        )
+    }
+}
+
+// Adds a new span as a child of the innermost open span, returning its index.
+fn push_span(spans: &mut Vec<Span>, open: &[usize], kind: SpanKind, label: String) -> usize {
+    let idx = spans.len();
+    let parent = *open.last().unwrap();
+    spans.push(Span::new(idx, kind, label, Some(parent)));
+    spans[parent].children.push(idx);
+    idx
+}
+
+// Totals the gas and timing of each span with that of its descendants.
+// Spans are opened after their parents, so visiting them in reverse totals children first.
+fn sum_totals(spans: &mut [Span]) {
+    for idx in (0..spans.len()).rev() {
+        let span = &spans[idx];
+        let (mut gas, mut gas_sum) = (span.self_gas.clone(), span.self_gas_sum);
+        let (mut timing, mut timing_sum) = (span.self_timing.clone(), span.self_timing_sum);
+        for child in span.children.iter().map(|c| &spans[*c]) {
+            gas_sum += child.total_gas_sum;
+            for (name, c) in &child.total_gas {
+                *gas.entry(name.clone()).or_insert_with(GasCharge::zero) += *c;
+            }
+            timing_sum += child.total_timing_sum;
+            for (name, t) in &child.total_timing {
+                *timing.entry(name.clone()).or_default() += *t;
+            }
+        }
+        let span = &mut spans[idx];
+        (span.total_gas, span.total_gas_sum) = (gas, gas_sum);
+        (span.total_timing, span.total_timing_sum) = (timing, timing_sum);
+    }
+}
+
+/// The kind of section of a trace that a span covers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SpanKind {
+    /// The whole trace.
+    Root,
+    /// An inter-actor message send, from the call until its return.
+    Call,
+    /// A section explicitly delimited by the actor code.
+    Named,
+}
+
+/// An instrumentation record covering a period of an execution trace.
+#[derive(Clone, Debug)]
+pub struct Span {
+    index: usize,
+    kind: SpanKind,
+    label: String,
+    attrs: Vec<(String, String)>,
+    call: Option<CallInfo>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    self_gas: HashMap<String, GasCharge>,
+    self_gas_sum: GasCharge,
+    self_timing: HashMap<String, ChargeTiming>,
+    self_timing_sum: ChargeTiming,
+    // Totals including descendants, computed once the analysis is built.
+    total_gas: HashMap<String, GasCharge>,
+    total_gas_sum: GasCharge,
+    total_timing: HashMap<String, ChargeTiming>,
+    total_timing_sum: ChargeTiming,
+}
+
+impl Span {
+    fn new(index: usize, kind: SpanKind, label: String, parent: Option<usize>) -> Self {
+        Self {
+            index,
+            kind,
+            label,
+            attrs: Vec::new(),
+            call: None,
+            parent,
+            children: Vec::new(),
+            self_gas: HashMap::new(),
+            self_gas_sum: GasCharge::zero(),
+            self_timing: HashMap::new(),
+            self_timing_sum: ChargeTiming::default(),
+            total_gas: HashMap::new(),
+            total_gas_sum: GasCharge::zero(),
+            total_timing: HashMap::new(),
+            total_timing_sum: ChargeTiming::default(),
+        }
+    }
+
+    /// The index of this span in the analysis, in the order spans were opened.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn kind(&self) -> SpanKind {
+        self.kind
+    }
+
+    /// The label with which this span was opened.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// The attributes with which a named span was opened.
+    pub fn attrs(&self) -> &[(String, String)] {
+        &self.attrs
+    }
+
+    /// The call covered by a call span.
+    pub fn call(&self) -> Option<&CallInfo> {
+        self.call.as_ref()
+    }
+
+    /// A name for this span, like "Root", "Call(100->f06::4)" or "Span(load_deals)".
+    pub fn name(&self) -> String {
+        match self.kind {
+            SpanKind::Root | SpanKind::Call => self.label.clone(),
+            SpanKind::Named => format!("Span({})", self.label),
+        }
+    }
+
+    /// An identifier for this span that is unique within the analysis, like "3-Span(load_deals)".
+    pub fn id(&self) -> String {
+        format!("{}-{}", self.index, self.name())
+    }
+
+    /// Returns the gas consumed directly by this span, excluding by its children.
+    pub fn self_gas(&self) -> GasCharge {
+        self.self_gas_sum
+    }
+
+    /// Returns the gas consumed directly by this span, excluding by its children, by charge name.
+    pub fn self_gas_by_name(&self) -> &HashMap<String, GasCharge> {
+        &self.self_gas
+    }
+
+    /// Adds to this span's self gas.
+    fn add_self_gas(&mut self, label: String, c: GasCharge) {
+        self.self_gas_sum += c;
+        *self.self_gas.entry(label).or_insert_with(GasCharge::zero) += c;
+    }
+
+    /// Returns the time of the timed charges made directly by this span, excluding by its
+    /// children.
+    pub fn self_timing(&self) -> ChargeTiming {
+        self.self_timing_sum
+    }
+
+    /// Returns the time of the timed charges made directly by this span, excluding by its
+    /// children, by charge name.
+    pub fn self_timing_by_name(&self) -> &HashMap<String, ChargeTiming> {
+        &self.self_timing
+    }
+
+    /// Adds a timed charge to this span's self timing.
+    fn add_self_timing(&mut self, label: String, elapsed: Duration, c: GasCharge) {
+        let timing = ChargeTiming::new(elapsed, c);
+        self.self_timing_sum += timing;
+        *self.self_timing.entry(label).or_default() += timing;
+    }
+}
+
+/// The message send covered by a call span.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallInfo {
+    pub from: ActorID,
+    pub to: Address,
+    pub method: MethodNum,
+    /// The code of the receiving actor, if it was invoked.
+    pub code: Option<Cid>,
+}
+
+/// How gas amounts are shown in span reports.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GasFormat {
+    /// Total gas, summing the dimensions, e.g. "ipld_get=300".
+    #[default]
+    Total,
+    /// Total gas followed by its compute and other dimensions,
+    /// e.g. "ipld_get=300 (compute=100, other=200)".
+    Dimensions,
+}
+
+pub(crate) fn format_gas_bits(
+    sum: GasCharge,
+    charges: &HashMap<String, GasCharge>,
+    format: GasFormat,
+) -> String {
+    let mut bits = vec![("sum", sum)];
+    for (k, v) in charges {
+        bits.push((k.as_str(), *v));
+    }
+    // Sort by descending total, which will leave "sum" first.
+    bits.sort_by_key(|(_, v)| std::cmp::Reverse(v.total()));
+    bits.iter()
+        .map(|(k, v)| match format {
+            GasFormat::Total => format!("{}={}", k, v.total().to_formatted_string(&Locale::en)),
+            GasFormat::Dimensions => format!(
+                "{}={} (compute={}, other={})",
+                k,
+                v.total().to_formatted_string(&Locale::en),
+                v.compute().to_formatted_string(&Locale::en),
+                v.other().to_formatted_string(&Locale::en),
+            ),
+        })
+        .join(", ")
+}
+
+/// A gas charge amount.
+/// Gas is charged along multiple dimensions, though for now (FVM v2) it's accurate to simply sum
+/// these dimensions into a total scalar gas cost.
+/// The dimensions are kept separately through aggregation, so reports can show whether compute
+/// or other (e.g. storage) gas dominates.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GasCharge {
+    compute_milli: u64,
+    other_milli: u64,
+}
+
+impl GasCharge {
+    /// Creates a new gas charge amount.
+    /// Note that parameters are milligas.
+    pub fn new_millis(compute_milli: u64, other_milli: u64) -> Self {
+        Self { compute_milli, other_milli }
+    }
+
+    /// Creates a zero gas charge amount.
+    pub fn zero() -> Self {
+        Self::new_millis(0, 0)
+    }
+
+    /// Returns the total gas charge amount, summing the dimensions.
+    pub fn total(&self) -> u64 {
+        milli_to_gas(self.total_milli())
+    }
+
+    /// Returns the total gas charge amount in milligas.
+    pub fn total_milli(&self) -> u64 {
+        self.compute_milli + self.other_milli
+    }
+
+    /// Returns the compute gas charge amount.
+    pub fn compute(&self) -> u64 {
+        milli_to_gas(self.compute_milli)
+    }
+
+    /// Returns the compute gas charge amount in milligas.
+    pub fn compute_milli(&self) -> u64 {
+        self.compute_milli
+    }
+
+    /// Returns the other (non-compute, e.g. storage) gas charge amount.
+    pub fn other(&self) -> u64 {
+        milli_to_gas(self.other_milli)
+    }
+
+    /// Returns the other (non-compute, e.g. storage) gas charge amount in milligas.
+    pub fn other_milli(&self) -> u64 {
+        self.other_milli
+    }
+}
+
+// Converts milligas to gas, rounding up any non-zero whole amount.
+pub(crate) fn milli_to_gas(millis: u64) -> u64 {
+    let units = millis / 1000;
+    // Round up
+    if units != 0 && millis % 1000 != 0 {
+        units + 1
+    } else {
+        units
+    }
+}
+
+impl Add for GasCharge {
+    type Output = GasCharge;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        GasCharge::new_millis(
+            self.compute_milli + rhs.compute_milli,
+            self.other_milli + rhs.other_milli,
+        )
+    }
+}
+
+impl AddAssign for GasCharge {
+    fn add_assign(&mut self, rhs: Self) {
+        self.compute_milli += rhs.compute_milli;
+        self.other_milli += rhs.other_milli;
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use num_format::{Locale, ToFormattedString};
+
+use crate::analysis::{GasCharge, TraceAnalysis};
+
+/// A column of a span table.
+/// Gas amounts are in gas, rounded up from milligas.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SpanColumn {
+    /// The span's identifier, like "3-Span(load_deals)".
+    Span,
+    /// The span's depth below the root.
+    Depth,
+    /// Gas consumed directly by the span, including by any collapsed descendants.
+    SelfGas,
+    /// Gas consumed by the span and its descendants.
+    TotalGas,
+    /// The compute dimension of the total gas.
+    Compute,
+    /// The other (non-compute, e.g. storage) dimension of the total gas.
+    Other,
+    /// The names of the charges of most total gas, up to some number, with their amounts,
+    /// e.g. "ipld_get=300, wasm_exec=200".
+    TopCharges(usize),
+}
+
+impl SpanColumn {
+    fn heading(&self) -> &'static str {
+        match self {
+            SpanColumn::Span => "Span",
+            SpanColumn::Depth => "Depth",
+            SpanColumn::SelfGas => "Self gas",
+            SpanColumn::TotalGas => "Total gas",
+            SpanColumn::Compute => "Compute gas",
+            SpanColumn::Other => "Other gas",
+            SpanColumn::TopCharges(_) => "Top charges",
+        }
+    }
+}
+
+/// The order of the rows of a span table.
+/// Spans of equal gas are kept in trace order.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SpanOrder {
+    /// The order in which spans were opened, so children follow their parents.
+    #[default]
+    Trace,
+    /// Descending total gas.
+    TotalGas,
+    /// Descending self gas.
+    SelfGas,
+}
+
+/// Options for exporting a trace analysis as a table of spans.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableOptions {
+    pub columns: Vec<SpanColumn>,
+    pub order: SpanOrder,
+    /// Spans with less total gas than this are collapsed into their nearest shown ancestor,
+    /// their gas counted as the ancestor's self gas. The root is always shown.
+    pub collapse_below: u64,
+}
+
+impl Default for TableOptions {
+    /// Span, self and total gas, and the top three charges, in trace order.
+    fn default() -> Self {
+        Self {
+            columns: vec![
+                SpanColumn::Span,
+                SpanColumn::SelfGas,
+                SpanColumn::TotalGas,
+                SpanColumn::TopCharges(3),
+            ],
+            order: SpanOrder::Trace,
+            collapse_below: 0,
+        }
+    }
+}
+
+impl TableOptions {
+    pub fn new(columns: Vec<SpanColumn>) -> Self {
+        Self { columns, ..Default::default() }
+    }
+
+    /// Sorts rows in some order.
+    pub fn sorted_by(mut self, order: SpanOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Collapses spans with less total gas than some amount.
+    pub fn collapse_below(mut self, gas: u64) -> Self {
+        self.collapse_below = gas;
+        self
+    }
+}
+
+/// Tabular exports of a trace analysis, one row per span, for spreadsheets and pull request
+/// comments.
+impl TraceAnalysis {
+    /// Formats spans as CSV, with a header row.
+    /// Gas amounts are plain integers, so spreadsheets read them as numbers.
+    pub fn to_csv(&self, options: &TableOptions) -> String {
+        let headings = options.columns.iter().map(|c| csv_field(c.heading())).join(",");
+        let rows = self.table_rows(options).into_iter().map(|row| {
+            row.iter().map(|cell| csv_field(&cell.render(|gas| gas.to_string()))).join(",")
+        });
+        std::iter::once(headings).chain(rows).join("\n")
+    }
+
+    /// Formats spans as a GitHub-flavoured Markdown table.
+    /// Gas amounts are right-aligned, with thousands separators.
+    pub fn to_markdown(&self, options: &TableOptions) -> String {
+        let separated = |gas: u64| gas.to_formatted_string(&Locale::en);
+        let headings = options.columns.iter().map(|c| c.heading()).join(" | ");
+        let alignments = options
+            .columns
+            .iter()
+            .map(|c| match c {
+                SpanColumn::Span | SpanColumn::TopCharges(_) => "---",
+                _ => "---:",
+            })
+            .join(" | ");
+        let rows = self.table_rows(options).into_iter().map(|row| {
+            let cells = row.iter().map(|cell| markdown_cell(&cell.render(separated))).join(" | ");
+            format!("| {} |", cells)
+        });
+        [format!("| {} |", headings), format!("| {} |", alignments)]
+            .into_iter()
+            .chain(rows)
+            .join("\n")
+    }
+
+    // Computes the cells of each shown span, in order.
+    fn table_rows(&self, options: &TableOptions) -> Vec<Vec<Cell>> {
+        let threshold = options.collapse_below.saturating_mul(1000);
+        // The shown span into which each span is collapsed, which is itself if shown.
+        let mut shown_as: Vec<usize> = Vec::with_capacity(self.spans().len());
+        let mut self_gas: HashMap<usize, GasCharge> = HashMap::new();
+        let mut collapsed: HashMap<usize, usize> = HashMap::new();
+        for span in self.spans() {
+            let target = match self.parent(span) {
+                Some(parent) if self.total_gas(span).total_milli() < threshold => {
+                    let target = shown_as[parent.index()];
+                    *collapsed.entry(target).or_default() += 1;
+                    target
+                }
+                _ => span.index(),
+            };
+            shown_as.push(target);
+            *self_gas.entry(target).or_default() += span.self_gas();
+        }
+
+        let rows = self
+            .spans()
+            .iter()
+            .filter(|span| shown_as[span.index()] == span.index())
+            .map(|span| (span, self_gas[&span.index()], self.total_gas(span)));
+        let rows = match options.order {
+            SpanOrder::Trace => rows.collect_vec(),
+            SpanOrder::TotalGas => {
+                rows.sorted_by_key(|r| std::cmp::Reverse(r.2.total_milli())).collect_vec()
+            }
+            SpanOrder::SelfGas => {
+                rows.sorted_by_key(|r| std::cmp::Reverse(r.1.total_milli())).collect_vec()
+            }
+        };
+        rows.into_iter()
+            .map(|(span, self_gas, total)| {
+                options
+                    .columns
+                    .iter()
+                    .map(|column| match column {
+                        SpanColumn::Span => match collapsed.get(&span.index()) {
+                            Some(n) => Cell::Text(format!("{} (+{} collapsed)", span.id(), n)),
+                            None => Cell::Text(span.id()),
+                        },
+                        SpanColumn::Depth => Cell::Text((self.path(span).len() - 1).to_string()),
+                        SpanColumn::SelfGas => Cell::Gas(self_gas.total()),
+                        SpanColumn::TotalGas => Cell::Gas(total.total()),
+                        SpanColumn::Compute => Cell::Gas(total.compute()),
+                        SpanColumn::Other => Cell::Gas(total.other()),
+                        SpanColumn::TopCharges(n) => Cell::Charges(
+                            self.total_gas_by_name(span)
+                                .into_iter()
+                                .map(|(name, c)| (name, c.total()))
+                                .sorted_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)))
+                                .take(*n)
+                                .collect(),
+                        ),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+// A table cell, rendered differently by each format.
+enum Cell {
+    Text(String),
+    Gas(u64),
+    Charges(Vec<(String, u64)>),
+}
+
+impl Cell {
+    fn render(&self, gas: impl Fn(u64) -> String) -> String {
+        match self {
+            Cell::Text(text) => text.clone(),
+            Cell::Gas(amount) => gas(*amount),
+            Cell::Charges(charges) => {
+                charges.iter().map(|(name, amount)| format!("{}={}", name, gas(*amount))).join(", ")
+            }
+        }
+    }
+}
+
+// Quotes a CSV field if it contains a delimiter, quote or line break (RFC 4180).
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Escapes the characters that would break a Markdown table row.
+fn markdown_cell(cell: &str) -> String {
+    cell.replace('|', "\\|").replace('\n', " ")
+}
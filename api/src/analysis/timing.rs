@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::ops::{Add, AddAssign};
+use std::time::Duration;
+
+use itertools::Itertools;
+use num_format::{Locale, ToFormattedString};
+
+use crate::analysis::{GasCharge, Span, TraceAnalysis};
+
+/// The wall-clock time taken by gas charges, with the gas charged for them, for comparing
+/// the price list with the real cost of execution.
+/// Only charges for which the VM recorded a time are included, so rates aren't diluted by
+/// untimed charges. The reference FVM records times only when built for gas calibration,
+/// which the `calibration` feature of the `vm` crate enables.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChargeTiming {
+    elapsed: Duration,
+    gas: GasCharge,
+    count: u64,
+}
+
+impl ChargeTiming {
+    /// Creates the timing of a single charge.
+    pub fn new(elapsed: Duration, gas: GasCharge) -> Self {
+        Self { elapsed, gas, count: 1 }
+    }
+
+    /// The total time taken by the timed charges.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The total gas of the timed charges.
+    pub fn gas(&self) -> GasCharge {
+        self.gas
+    }
+
+    /// The number of timed charges.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Nanoseconds per unit of gas, summing the dimensions, or None if no gas was timed.
+    pub fn ns_per_gas(&self) -> Option<f64> {
+        ns_per_milli(self.elapsed, self.gas.total_milli())
+    }
+
+    /// Nanoseconds per unit of compute gas, or None if no compute gas was timed.
+    /// Compute gas is priced to reflect execution time, while other gas pays for storage,
+    /// so this is usually the rate to compare with the price list's target.
+    pub fn ns_per_compute_gas(&self) -> Option<f64> {
+        ns_per_milli(self.elapsed, self.gas.compute_milli())
+    }
+}
+
+impl Add for ChargeTiming {
+    type Output = ChargeTiming;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        ChargeTiming {
+            elapsed: self.elapsed + rhs.elapsed,
+            gas: self.gas + rhs.gas,
+            count: self.count + rhs.count,
+        }
+    }
+}
+
+impl AddAssign for ChargeTiming {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl TraceAnalysis {
+    /// Returns the time of the timed charges of a span, including by its descendants.
+    pub fn total_timing(&self, span: &Span) -> ChargeTiming {
+        span.total_timing_sum
+    }
+
+    /// Returns the time of the timed charges of a span, including by its descendants,
+    /// by charge name.
+    pub fn total_timing_by_name(&self, span: &Span) -> HashMap<String, ChargeTiming> {
+        span.total_timing.clone()
+    }
+
+    /// Formats the timing of each charge name over the whole trace, one per line,
+    /// in descending order of nanoseconds per compute gas, e.g.
+    /// "OnHashing: count=3 elapsed=1.2µs gas=2,400 compute=2,400 ns/gas=0.50 ns/compute=0.50".
+    pub fn format_charge_timing(&self) -> String {
+        self.total_timing_by_name(self.root())
+            .into_iter()
+            .sorted_by(|a, b| {
+                let rate = |t: &ChargeTiming| t.ns_per_compute_gas().unwrap_or(f64::INFINITY);
+                rate(&b.1).total_cmp(&rate(&a.1)).then(a.0.cmp(&b.0))
+            })
+            .map(|(name, timing)| format!("{}: {}", name, format_timing(&timing)))
+            .join("\n")
+    }
+
+    /// Formats the self and total timing of each span, one per line, e.g.
+    /// "Span[1-Call(100->f06::4), self: {count=2 ...}, total: {count=5 ...}]".
+    /// Spans without any timed charges are omitted.
+    pub fn format_span_timing(&self) -> String {
+        self.spans()
+            .iter()
+            .filter_map(|span| {
+                let total = self.total_timing(span);
+                (total.count > 0).then(|| {
+                    format!(
+                        "Span[{}, self: {{{}}}, total: {{{}}}]",
+                        span.id(),
+                        format_timing(&span.self_timing()),
+                        format_timing(&total)
+                    )
+                })
+            })
+            .join("\n")
+    }
+}
+
+fn ns_per_milli(elapsed: Duration, milli: u64) -> Option<f64> {
+    (milli > 0).then(|| elapsed.as_nanos() as f64 * 1000.0 / milli as f64)
+}
+
+fn format_timing(timing: &ChargeTiming) -> String {
+    let rate = |r: Option<f64>| r.map_or_else(|| "-".to_string(), |r| format!("{:.2}", r));
+    format!(
+        "count={} elapsed={:?} gas={} compute={} ns/gas={} ns/compute={}",
+        timing.count,
+        timing.elapsed,
+        timing.gas.total().to_formatted_string(&Locale::en),
+        timing.gas.compute().to_formatted_string(&Locale::en),
+        rate(timing.ns_per_gas()),
+        rate(timing.ns_per_compute_gas()),
+    )
+}
@@ -0,0 +1,56 @@
+//! Helpers for inspecting actor events (FIP-0049).
+//! Built-in actors name each event's type with a "$type" entry, e.g. "deal-published",
+//! followed by entries for its fields, with CBOR values.
+
+use fvm_ipld_encoding::from_slice;
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_shared::event::{ActorEvent, StampedEvent};
+use itertools::Itertools;
+use serde::de::DeserializeOwned;
+
+use crate::trace::decode::format_block;
+use crate::trace::{ExecutionEvent, ExecutionTrace};
+
+/// The key of the entry naming an event's type.
+pub const EVENT_TYPE_KEY: &str = "$type";
+
+/// Returns the type of an event, from its "$type" entry, if it has one.
+pub fn event_type(event: &ActorEvent) -> Option<String> {
+    event_value(event, EVENT_TYPE_KEY)
+}
+
+/// Decodes the value of an event's first entry with some key, if present and of the type.
+pub fn event_value<T: DeserializeOwned>(event: &ActorEvent, key: &str) -> Option<T> {
+    let entry = event.entries.iter().find(|e| e.key == key)?;
+    from_slice(&entry.value).ok()
+}
+
+/// Formats an event's entries for display, like `$type="verifier-balance", verifier=1000`.
+/// Values are shown in CBOR diagnostic notation.
+pub fn format_event(event: &ActorEvent) -> String {
+    event
+        .entries
+        .iter()
+        .map(|e| {
+            let value = format_block(&IpldBlock { codec: e.codec, data: e.value.clone() });
+            format!("{}={}", e.key, value)
+        })
+        .join(", ")
+}
+
+impl ExecutionTrace {
+    /// Returns the actor events emitted during execution, in order.
+    /// Unlike the events of an execution result, these include events emitted by calls that
+    /// later aborted.
+    pub fn actor_events(&self) -> Vec<StampedEvent> {
+        self.events()
+            .iter()
+            .filter_map(|e| match e {
+                ExecutionEvent::ActorEvent { emitter, event } => {
+                    Some(StampedEvent::new(*emitter, event.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
@@ -1,10 +1,17 @@
 use fvm_shared::econ::TokenAmount;
+use fvm_shared::event::StampedEvent;
 use fvm_shared::receipt::Receipt;
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
 use vm_api::{ActorState, MessageResult};
 
 pub mod analysis;
 pub mod bench;
 pub mod blockstore;
+pub mod events;
+pub mod names;
+#[cfg(feature = "persistence")]
+pub mod persist;
 pub mod trace;
 pub mod wrangler;
 
@@ -12,7 +19,8 @@ use trace::ExecutionTrace;
 
 /// The result of a message execution.
 /// This duplicates a lot from an FVM-internal type, but is independent of VM.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
 pub struct ExecutionResult {
     /// Message receipt for the transaction.
     pub receipt: Receipt,
@@ -26,6 +34,10 @@ pub struct ExecutionResult {
     pub base_fee_burn: TokenAmount,
     pub over_estimation_burn: TokenAmount,
 
+    /// Actor events emitted by the message, in order, excluding those of calls that aborted.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub events: Vec<StampedEvent>,
+
     /// Execution trace information, for debugging.
     pub trace: ExecutionTrace,
     pub message: String,
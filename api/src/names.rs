@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, HashMap};
+
+use cid::Cid;
+use fvm_shared::address::Address;
+use fvm_shared::{ActorID, MethodNum, METHOD_CONSTRUCTOR, METHOD_SEND};
+
+/// Resolves actor IDs, actor code and method numbers to human-readable names,
+/// for formatting traces and analyses.
+/// Actors are named explicitly, or else by address. Methods are named according to the type of
+/// the receiving actor's code, or else by number.
+#[derive(Clone, Debug, Default)]
+pub struct NameResolver {
+    actor_names: HashMap<ActorID, String>,
+    actor_codes: HashMap<ActorID, Cid>,
+    code_types: HashMap<Cid, String>,
+    method_names: HashMap<(String, MethodNum), String>,
+}
+
+impl NameResolver {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a resolver that names built-in actor code by type, e.g. "Miner".
+    pub fn with_builtin_manifest(manifest: &BTreeMap<Cid, vm_api::builtin::Type>) -> Self {
+        let mut resolver = Self::new();
+        for (code, typ) in manifest {
+            resolver.register_code_type(*code, &format!("{:?}", typ));
+        }
+        resolver
+    }
+
+    /// Registers a name for an actor, e.g. "faucet".
+    pub fn register_actor_name(&mut self, id: ActorID, name: &str) {
+        self.actor_names.insert(id, name.to_string());
+    }
+
+    /// Registers the code CID of an actor, from which its type may be resolved.
+    pub fn register_actor_code(&mut self, id: ActorID, code: Cid) {
+        self.actor_codes.insert(id, code);
+    }
+
+    /// Registers the name of the actor type implemented by some code, e.g. "Miner".
+    pub fn register_code_type(&mut self, code: Cid, type_name: &str) {
+        self.code_types.insert(code, type_name.to_string());
+    }
+
+    /// Registers a name for a method of an actor type.
+    pub fn register_method(&mut self, type_name: &str, method: MethodNum, name: &str) {
+        self.method_names.insert((type_name.to_string(), method), name.to_string());
+    }
+
+    /// Registers a method of an actor type that is exported with an FRC-0042 hashed
+    /// method number.
+    pub fn register_exported_method(&mut self, type_name: &str, name: &str) {
+        self.register_method(type_name, frc42_method_number(name), name);
+    }
+
+    /// Returns the name of an actor, or its ID address if it has no registered name.
+    pub fn actor_name(&self, id: ActorID) -> String {
+        self.actor_names.get(&id).cloned().unwrap_or_else(|| Address::new_id(id).to_string())
+    }
+
+    /// Returns the name of the actor at an ID address, or the address itself.
+    pub fn address_name(&self, addr: &Address) -> String {
+        match addr.id() {
+            Ok(id) => self.actor_name(id),
+            Err(_) => addr.to_string(),
+        }
+    }
+
+    /// Returns the type name of some actor code, if known.
+    pub fn code_type(&self, code: &Cid) -> Option<&str> {
+        self.code_types.get(code).map(String::as_str)
+    }
+
+    /// Returns the type name of an actor, if its code is known.
+    pub fn actor_type(&self, id: ActorID) -> Option<&str> {
+        self.actor_codes.get(&id).and_then(|code| self.code_type(code))
+    }
+
+    /// Returns the name of a method of an actor type, or the method number if not known.
+    pub fn method_name(&self, type_name: Option<&str>, method: MethodNum) -> String {
+        let registered = type_name.and_then(|t| self.method_names.get(&(t.to_string(), method)));
+        match registered {
+            Some(name) => name.clone(),
+            None if method == METHOD_SEND => "Send".to_string(),
+            None if method == METHOD_CONSTRUCTOR && type_name.is_some() => {
+                "Constructor".to_string()
+            }
+            None => method.to_string(),
+        }
+    }
+
+    /// Returns a label for a call, like "Call(faucet->Init::Exec)".
+    /// The receiver's code, if given, takes precedence over code registered for the receiver.
+    pub fn call_label(
+        &self,
+        from: ActorID,
+        to: &Address,
+        method: MethodNum,
+        code: Option<&Cid>,
+    ) -> String {
+        let type_name = match code {
+            Some(code) => self.code_type(code),
+            None => to.id().ok().and_then(|id| self.actor_type(id)),
+        };
+        format!(
+            "Call({}->{}::{})",
+            self.actor_name(from),
+            self.address_name(to),
+            self.method_name(type_name, method)
+        )
+    }
+}
+
+/// Computes the FRC-0042 method number for an exported method name.
+/// The number is the first 4-byte big-endian chunk of blake2b-512("1|" + name) that is
+/// at least 2^24.
+pub fn frc42_method_number(name: &str) -> MethodNum {
+    let digest = blake2b_simd::Params::new().hash_length(64).hash(format!("1|{}", name).as_bytes());
+    let bytes = digest.as_bytes();
+    (0..bytes.len() / 4)
+        .map(|i| u32::from_be_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap()) as MethodNum)
+        .find(|n| *n >= 1 << 24)
+        .expect("no valid FRC-0042 method number")
+}
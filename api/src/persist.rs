@@ -0,0 +1,78 @@
+//! JSON and DAG-CBOR encodings of execution traces and results, so they can be saved from one
+//! run and analysed later or elsewhere.
+
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::trace::ExecutionTrace;
+use crate::ExecutionResult;
+
+impl ExecutionTrace {
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        to_json(self)
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        from_json(json)
+    }
+
+    pub fn to_cbor(&self) -> anyhow::Result<Vec<u8>> {
+        to_cbor(self)
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> anyhow::Result<Self> {
+        from_cbor(bytes)
+    }
+}
+
+impl ExecutionResult {
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        to_json(self)
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        from_json(json)
+    }
+
+    pub fn to_cbor(&self) -> anyhow::Result<Vec<u8>> {
+        to_cbor(self)
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> anyhow::Result<Self> {
+        from_cbor(bytes)
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> anyhow::Result<String> {
+    serde_json::to_string(value).context("failed to encode JSON")
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> anyhow::Result<T> {
+    serde_json::from_str(json).context("failed to decode JSON")
+}
+
+fn to_cbor<T: Serialize>(value: &T) -> anyhow::Result<Vec<u8>> {
+    fvm_ipld_encoding::to_vec(value).context("failed to encode DAG-CBOR")
+}
+
+fn from_cbor<T: DeserializeOwned>(bytes: &[u8]) -> anyhow::Result<T> {
+    fvm_ipld_encoding::from_slice(bytes).context("failed to decode DAG-CBOR")
+}
+
+/// Serializes an ErrorNumber as its numeric value.
+pub(crate) mod errno {
+    use fvm_shared::error::ErrorNumber;
+    use num_traits::FromPrimitive;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(errno: &ErrorNumber, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u32(*errno as u32)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<ErrorNumber, D::Error> {
+        let n = u32::deserialize(d)?;
+        ErrorNumber::from_u32(n)
+            .ok_or_else(|| de::Error::custom(format!("unknown error number {}", n)))
+    }
+}
@@ -0,0 +1,177 @@
+use serde_json::{json, Value};
+
+use crate::events::format_event;
+use crate::trace::{ExecutionEvent, ExecutionTrace};
+
+// All events are placed on a single thread of a single process.
+const PID: u32 = 1;
+const TID: u32 = 1;
+
+/// Export of an execution trace in the Chrome Trace Event Format, which can be loaded by
+/// chrome://tracing and https://ui.perfetto.dev.
+/// Execution has no meaningful wall-clock time, so the time axis is cumulative milligas:
+/// one "microsecond" in the viewer corresponds to one milligas.
+impl ExecutionTrace {
+    /// Exports the trace as Chrome Trace Event Format JSON.
+    /// Each call and named span is a duration slice, from a begin (`B`) event to an end (`E`)
+    /// event, spanning the gas charged between them. Spans left open when their call returns,
+    /// and calls and spans left open at the end of the trace, are closed and marked `unclosed`.
+    /// Each gas charge is an instant event, and the cumulative gas is a counter.
+    pub fn to_chrome_trace(&self, name: &str) -> Value {
+        let mut events = vec![
+            json!({ "ph": "M", "pid": PID, "name": "process_name", "args": { "name": name } }),
+            json!({
+                "ph": "M", "pid": PID, "tid": TID, "name": "thread_name",
+                "args": { "name": "message" },
+            }),
+        ];
+        // Cumulative milligas charged so far.
+        let mut now: u64 = 0;
+        // The names of the calls and spans currently open, innermost last, with span labels.
+        let mut open: Vec<(String, Option<String>)> = Vec::new();
+        for event in self.events() {
+            match event {
+                ExecutionEvent::GasCharge { name, compute_milli, other_milli, elapsed } => {
+                    let mut args =
+                        json!({ "compute_milli": compute_milli, "other_milli": other_milli });
+                    if let Some(elapsed) = elapsed {
+                        args["elapsed_ns"] = json!(elapsed.as_nanos() as u64);
+                    }
+                    events.push(json!({
+                        "ph": "i", "s": "t", "pid": PID, "tid": TID, "ts": now,
+                        "name": name, "cat": "gas", "args": args,
+                    }));
+                    now += compute_milli + other_milli;
+                    events.push(json!({
+                        "ph": "C", "pid": PID, "ts": now, "name": "gas",
+                        "args": { "milligas": now },
+                    }));
+                }
+                ExecutionEvent::Call { from, to, method, value, gas_limit, read_only, .. } => {
+                    let args = json!({
+                        "from": from,
+                        "to": to.to_string(),
+                        "method": method,
+                        "value": value.to_string(),
+                        "gas_limit": gas_limit,
+                        "read_only": read_only,
+                    });
+                    let name = format!("Call({}->{}::{})", from, to, method);
+                    events.push(slice_event("B", now, &name, "call", args));
+                    open.push((name, None));
+                }
+                ExecutionEvent::CallReturn { exit_code, .. } => {
+                    let args = json!({ "exit_code": exit_code.value() });
+                    end_call(&mut events, &mut open, now, args);
+                }
+                ExecutionEvent::CallError { reason, errno } => {
+                    let args = json!({ "errno": errno.to_string(), "reason": reason });
+                    end_call(&mut events, &mut open, now, args);
+                }
+                ExecutionEvent::InvokeActor { cid } => {
+                    events.push(json!({
+                        "ph": "i", "s": "t", "pid": PID, "tid": TID, "ts": now,
+                        "name": "InvokeActor", "cat": "call",
+                        "args": { "code": cid.to_string() },
+                    }));
+                }
+                ExecutionEvent::Ipld { op, cid, size } => {
+                    events.push(json!({
+                        "ph": "i", "s": "t", "pid": PID, "tid": TID, "ts": now,
+                        "name": format!("Ipld{:?}", op), "cat": "ipld",
+                        "args": { "cid": cid.to_string(), "size": size },
+                    }));
+                }
+                ExecutionEvent::Log { msg } => {
+                    events.push(json!({
+                        "ph": "i", "s": "t", "pid": PID, "tid": TID, "ts": now,
+                        "name": "Log", "cat": "log",
+                        "args": { "msg": msg },
+                    }));
+                }
+                ExecutionEvent::ActorEvent { emitter, event } => {
+                    events.push(json!({
+                        "ph": "i", "s": "t", "pid": PID, "tid": TID, "ts": now,
+                        "name": "ActorEvent", "cat": "event",
+                        "args": { "emitter": emitter, "entries": format_event(event) },
+                    }));
+                }
+                ExecutionEvent::SpanStart { label, attrs } => {
+                    let args: serde_json::Map<String, Value> =
+                        attrs.iter().map(|(k, v)| (k.clone(), json!(v))).collect();
+                    let name = format!("Span({})", label);
+                    events.push(slice_event("B", now, &name, "span", Value::Object(args)));
+                    open.push((name, Some(label.clone())));
+                }
+                ExecutionEvent::SpanEnd { label } => {
+                    // Only spans opened within the innermost open call can be closed.
+                    let call_depth =
+                        open.iter().rposition(|(_, l)| l.is_none()).map_or(0, |i| i + 1);
+                    let matching = open[call_depth..]
+                        .iter()
+                        .rposition(|(_, l)| l.as_deref() == Some(label.as_str()));
+                    match matching {
+                        Some(i) => {
+                            close_unclosed(&mut events, &mut open, call_depth + i + 1, now);
+                            let (name, _) = open.pop().unwrap();
+                            events.push(slice_event("E", now, &name, "span", json!({})));
+                        }
+                        None => events.push(json!({
+                            "ph": "i", "s": "t", "pid": PID, "tid": TID, "ts": now,
+                            "name": format!("SpanEnd({})", label), "cat": "span",
+                            "args": { "unmatched": true },
+                        })),
+                    }
+                }
+                ExecutionEvent::Unrecognized { description } => {
+                    events.push(json!({
+                        "ph": "i", "s": "t", "pid": PID, "tid": TID, "ts": now,
+                        "name": "Unrecognized", "cat": "other",
+                        "args": { "description": description },
+                    }));
+                }
+            }
+        }
+        // Close any calls and spans left open by a truncated trace.
+        close_unclosed(&mut events, &mut open, 0, now);
+
+        json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ns",
+            "otherData": { "time_unit": "milligas" },
+        })
+    }
+}
+
+// A begin or end event of a duration slice. The viewer merges the arguments of both.
+fn slice_event(ph: &str, ts: u64, name: &str, cat: &str, args: Value) -> Value {
+    json!({ "ph": ph, "pid": PID, "tid": TID, "ts": ts, "name": name, "cat": cat, "args": args })
+}
+
+// Ends the innermost open call, first closing any spans opened within it.
+fn end_call(
+    events: &mut Vec<Value>,
+    open: &mut Vec<(String, Option<String>)>,
+    ts: u64,
+    args: Value,
+) {
+    if let Some(call) = open.iter().rposition(|(_, label)| label.is_none()) {
+        close_unclosed(events, open, call + 1, ts);
+        let (name, _) = open.pop().unwrap();
+        events.push(slice_event("E", ts, &name, "call", args));
+    }
+}
+
+// Ends the open calls and spans above some depth, marking them unclosed.
+fn close_unclosed(
+    events: &mut Vec<Value>,
+    open: &mut Vec<(String, Option<String>)>,
+    depth: usize,
+    ts: u64,
+) {
+    while open.len() > depth {
+        let (name, label) = open.pop().unwrap();
+        let cat = if label.is_some() { "span" } else { "call" };
+        events.push(slice_event("E", ts, &name, cat, json!({ "unclosed": true })));
+    }
+}
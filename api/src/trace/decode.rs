@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Write};
+
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::{from_slice, CBOR, DAG_CBOR, IPLD_RAW};
+use fvm_shared::MethodNum;
+use itertools::Itertools;
+use libipld_core::ipld::Ipld;
+use serde::de::DeserializeOwned;
+
+type Decoder = Box<dyn Fn(&[u8]) -> anyhow::Result<String>>;
+
+/// A registry of typed decoders for call parameters and return values, keyed by the
+/// receiving actor's type name (as resolved by a [`crate::names::NameResolver`]) and method.
+/// Values without a registered decoder, or that fail to decode as the registered type,
+/// are formatted in CBOR diagnostic notation.
+#[derive(Default)]
+pub struct DecoderRegistry {
+    params: HashMap<(String, MethodNum), Decoder>,
+    returns: HashMap<(String, MethodNum), Decoder>,
+}
+
+impl DecoderRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers the type of the parameters of a method, which are then formatted with Debug.
+    pub fn register_params<T: DeserializeOwned + Debug + 'static>(
+        &mut self,
+        type_name: &str,
+        method: MethodNum,
+    ) {
+        self.params.insert((type_name.to_string(), method), typed_decoder::<T>());
+    }
+
+    /// Registers the type of the return value of a method, which is then formatted with Debug.
+    pub fn register_return<T: DeserializeOwned + Debug + 'static>(
+        &mut self,
+        type_name: &str,
+        method: MethodNum,
+    ) {
+        self.returns.insert((type_name.to_string(), method), typed_decoder::<T>());
+    }
+
+    /// Formats the parameters of a call to a method of an actor type.
+    pub fn format_params(
+        &self,
+        type_name: Option<&str>,
+        method: MethodNum,
+        params: &IpldBlock,
+    ) -> String {
+        format_typed(&self.params, type_name, method, params)
+    }
+
+    /// Formats the value returned from a call to a method of an actor type.
+    pub fn format_return(
+        &self,
+        type_name: Option<&str>,
+        method: MethodNum,
+        return_value: &IpldBlock,
+    ) -> String {
+        format_typed(&self.returns, type_name, method, return_value)
+    }
+}
+
+fn typed_decoder<T: DeserializeOwned + Debug + 'static>() -> Decoder {
+    Box::new(|data| Ok(format!("{:?}", from_slice::<T>(data)?)))
+}
+
+fn format_typed(
+    decoders: &HashMap<(String, MethodNum), Decoder>,
+    type_name: Option<&str>,
+    method: MethodNum,
+    block: &IpldBlock,
+) -> String {
+    let decoder = type_name.and_then(|t| decoders.get(&(t.to_string(), method)));
+    match decoder.map(|decode| decode(&block.data)) {
+        Some(Ok(formatted)) => formatted,
+        // Fall back to the untyped form, which will likely show why the type didn't match.
+        _ => format_block(block),
+    }
+}
+
+/// Formats an IPLD block for display.
+/// CBOR data is shown in diagnostic notation, and other data as hex bytes.
+pub fn format_block(block: &IpldBlock) -> String {
+    match block.codec {
+        DAG_CBOR | CBOR => match cbor_diagnostic(&block.data) {
+            Ok(diag) => diag,
+            Err(e) => format!("{} (invalid CBOR: {})", hex_bytes(&block.data), e),
+        },
+        IPLD_RAW => hex_bytes(&block.data),
+        codec => format!("{} (codec {:#x})", hex_bytes(&block.data), codec),
+    }
+}
+
+/// Formats CBOR data in diagnostic notation (RFC 8949 section 8), e.g.
+/// `[1, h'0102', {"a": true}]`. CIDs (tag 42) are shown in their string form, like `42(bafy...)`.
+/// The data is decoded as DAG-CBOR, so other tags and non-string map keys are rejected.
+pub fn cbor_diagnostic(data: &[u8]) -> anyhow::Result<String> {
+    let ipld: Ipld = from_slice(data)?;
+    let mut out = String::new();
+    write_diagnostic(&mut out, &ipld)?;
+    Ok(out)
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    format!("h'{}'", bytes.iter().map(|b| format!("{:02x}", b)).join(""))
+}
+
+fn write_diagnostic(out: &mut String, ipld: &Ipld) -> anyhow::Result<()> {
+    match ipld {
+        Ipld::Null => out.push_str("null"),
+        Ipld::Bool(b) => write!(out, "{}", b)?,
+        Ipld::Integer(n) => write!(out, "{}", n)?,
+        Ipld::Float(f) => out.push_str(&format_float(*f)),
+        Ipld::String(text) => out.push_str(&serde_json::to_string(text)?),
+        Ipld::Bytes(bytes) => out.push_str(&hex_bytes(bytes)),
+        Ipld::List(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_diagnostic(out, item)?;
+            }
+            out.push(']');
+        }
+        Ipld::Map(entries) => {
+            out.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                out.push_str(&serde_json::to_string(key)?);
+                out.push_str(": ");
+                write_diagnostic(out, value)?;
+            }
+            out.push('}');
+        }
+        Ipld::Link(cid) => write!(out, "42({})", cid)?,
+    }
+    Ok(())
+}
+
+fn format_float(f: f64) -> String {
+    match f {
+        f if f == f64::INFINITY => "Infinity".to_string(),
+        f if f == f64::NEG_INFINITY => "-Infinity".to_string(),
+        f => format!("{:?}", f),
+    }
+}
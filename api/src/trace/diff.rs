@@ -0,0 +1,406 @@
+use cid::Cid;
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::{ActorID, MethodNum};
+use num_format::{Locale, ToFormattedString};
+use vm_api::trace::InvocationTrace;
+
+use crate::analysis::{milli_to_gas, GasCharge};
+use crate::names::NameResolver;
+use crate::trace::decode::{format_block, DecoderRegistry};
+use crate::trace::invocation::{invocations, Invocation};
+use crate::trace::query::{CallOutcome, CallRecord};
+use crate::trace::ExecutionTrace;
+
+/// A structural comparison of two executions of the same message, e.g. with two versions of
+/// an actor bundle, or before and after a change to an actor.
+/// The call trees are aligned by matching each call's subcalls in order by sender, receiver and
+/// method, so that a call added or removed in one execution doesn't misalign its siblings.
+/// Aligned calls are compared for changes in value, parameters and outcome, and gas.
+#[derive(Clone, Debug)]
+pub struct TraceDiff {
+    calls: Vec<CallDiff>,
+    with_gas: bool,
+}
+
+/// The comparison of a call in two executions.
+#[derive(Clone, Debug)]
+pub struct CallDiff {
+    pub from: ActorID,
+    pub to: Address,
+    pub method: MethodNum,
+    /// The code of the receiving actor, if it was invoked (preferring the later execution).
+    pub code: Option<Cid>,
+    pub kind: DiffKind,
+    /// Differences between the aligned calls. Empty for added and removed calls.
+    pub changes: Vec<CallChange>,
+    /// Gas charged by the call, including by its subcalls, before and after.
+    /// Zero where the call is absent, or when comparing traces without gas.
+    pub gas_before: GasCharge,
+    pub gas_after: GasCharge,
+    pub subcalls: Vec<CallDiff>,
+}
+
+/// Whether a call is present in both executions, or only one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DiffKind {
+    Matched,
+    /// The call is only in the later execution.
+    Added,
+    /// The call is only in the earlier execution.
+    Removed,
+}
+
+/// A difference between two aligned calls.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallChange {
+    Value {
+        before: TokenAmount,
+        after: TokenAmount,
+    },
+    Params {
+        before: Option<IpldBlock>,
+        after: Option<IpldBlock>,
+    },
+    /// The call ended differently, e.g. with a different exit code, or with an error rather than
+    /// a return.
+    Outcome {
+        before: CallOutcome,
+        after: CallOutcome,
+    },
+    /// The call returned with the same exit code but a different value.
+    ReturnValue {
+        before: Option<IpldBlock>,
+        after: Option<IpldBlock>,
+    },
+}
+
+impl TraceDiff {
+    /// Compares the calls of two execution traces, including their gas.
+    pub fn of_traces(before: &ExecutionTrace, after: &ExecutionTrace) -> Self {
+        let before = invocations(before.calls());
+        let after = invocations(after.calls());
+        Self { calls: diff_calls(&before, &after), with_gas: true }
+    }
+
+    /// Compares two invocation traces, e.g. from VMs other than the workbench.
+    /// Invocation traces don't record gas, so gas is not compared.
+    pub fn of_invocations(before: &InvocationTrace, after: &InvocationTrace) -> Self {
+        let before = [invocation_from_trace(before)];
+        let after = [invocation_from_trace(after)];
+        Self { calls: diff_calls(&before, &after), with_gas: false }
+    }
+
+    /// The aligned top-level calls. Usually this is a single call, the message.
+    pub fn calls(&self) -> &[CallDiff] {
+        &self.calls
+    }
+
+    /// Whether gas is compared.
+    pub fn with_gas(&self) -> bool {
+        self.with_gas
+    }
+
+    /// Whether the two executions made the same calls with the same outcomes.
+    /// Gas is not considered.
+    pub fn is_unchanged(&self) -> bool {
+        self.calls.iter().all(CallDiff::is_unchanged)
+    }
+
+    /// Returns the first call, in the order calls were made, that was added, removed or changed.
+    pub fn first_divergence(&self) -> Option<&CallDiff> {
+        self.calls.iter().find_map(CallDiff::first_divergence)
+    }
+
+    /// The change in gas charged by the top-level calls, in milligas.
+    pub fn gas_delta_milli(&self) -> i64 {
+        self.calls.iter().map(CallDiff::gas_delta_milli).sum()
+    }
+
+    /// Formats the diff as an indented call tree, one call per line, with changes beneath it.
+    /// Lines are marked "+" for added calls, "-" for removed calls, and "~" for changed calls.
+    /// The first divergence is marked with "<== first divergence".
+    pub fn format_with(&self, resolver: &NameResolver) -> String {
+        self.format_calls(resolver, None)
+    }
+
+    /// Formats the diff as for `format_with`, with parameters and return values decoded.
+    pub fn format_decoded(&self, resolver: &NameResolver, decoders: &DecoderRegistry) -> String {
+        self.format_calls(resolver, Some(decoders))
+    }
+
+    fn format_calls(&self, resolver: &NameResolver, decoders: Option<&DecoderRegistry>) -> String {
+        let first = self.first_divergence();
+        let mut lines = Vec::new();
+        for call in &self.calls {
+            call.format(&mut lines, 0, first, self.with_gas, resolver, decoders);
+        }
+        lines.join("\n")
+    }
+}
+
+impl CallDiff {
+    /// Whether this call and its subcalls are matched with no changes.
+    /// Gas is not considered.
+    pub fn is_unchanged(&self) -> bool {
+        self.kind == DiffKind::Matched
+            && self.changes.is_empty()
+            && self.subcalls.iter().all(CallDiff::is_unchanged)
+    }
+
+    /// The change in gas charged by this call, including its subcalls, in milligas.
+    pub fn gas_delta_milli(&self) -> i64 {
+        self.gas_after.total_milli() as i64 - self.gas_before.total_milli() as i64
+    }
+
+    fn is_divergent(&self) -> bool {
+        self.kind != DiffKind::Matched || !self.changes.is_empty()
+    }
+
+    fn first_divergence(&self) -> Option<&CallDiff> {
+        if self.is_divergent() {
+            return Some(self);
+        }
+        self.subcalls.iter().find_map(CallDiff::first_divergence)
+    }
+
+    fn format(
+        &self,
+        lines: &mut Vec<String>,
+        depth: usize,
+        first: Option<&CallDiff>,
+        with_gas: bool,
+        resolver: &NameResolver,
+        decoders: Option<&DecoderRegistry>,
+    ) {
+        let marker = match self.kind {
+            DiffKind::Added => "+",
+            DiffKind::Removed => "-",
+            DiffKind::Matched if self.changes.is_empty() => " ",
+            DiffKind::Matched => "~",
+        };
+        let indent = "  ".repeat(depth);
+        let mut line = format!(
+            "{} {}{}",
+            marker,
+            indent,
+            resolver.call_label(self.from, &self.to, self.method, self.code.as_ref())
+        );
+        if with_gas {
+            line += &match self.kind {
+                DiffKind::Matched => format!(
+                    " gas={}->{} ({})",
+                    format_gas(self.gas_before.total_milli()),
+                    format_gas(self.gas_after.total_milli()),
+                    format_delta(self.gas_delta_milli())
+                ),
+                DiffKind::Added => format!(" gas={}", format_gas(self.gas_after.total_milli())),
+                DiffKind::Removed => format!(" gas={}", format_gas(self.gas_before.total_milli())),
+            };
+        }
+        if first.is_some_and(|f| std::ptr::eq(f, self)) {
+            line += "  <== first divergence";
+        }
+        lines.push(line);
+
+        let type_name = match &self.code {
+            Some(code) => resolver.code_type(code),
+            None => self.to.id().ok().and_then(|id| resolver.actor_type(id)),
+        };
+        let params = |p: &Option<IpldBlock>| match (p, decoders) {
+            (None, _) => "none".to_string(),
+            (Some(p), Some(d)) => d.format_params(type_name, self.method, p),
+            (Some(p), None) => format_block(p),
+        };
+        let ret = |r: &Option<IpldBlock>| match (r, decoders) {
+            (None, _) => "none".to_string(),
+            (Some(r), Some(d)) => d.format_return(type_name, self.method, r),
+            (Some(r), None) => format_block(r),
+        };
+        for change in &self.changes {
+            let (field, before, after) = match change {
+                CallChange::Value { before, after } => {
+                    ("value", before.to_string(), after.to_string())
+                }
+                CallChange::Params { before, after } => ("params", params(before), params(after)),
+                CallChange::Outcome { before, after } => {
+                    ("outcome", format_outcome(before), format_outcome(after))
+                }
+                CallChange::ReturnValue { before, after } => ("return", ret(before), ret(after)),
+            };
+            lines.push(format!("  {}    {}: {} -> {}", indent, field, before, after));
+        }
+        for sub in &self.subcalls {
+            sub.format(lines, depth + 1, first, with_gas, resolver, decoders);
+        }
+    }
+}
+
+impl ExecutionTrace {
+    /// Compares this trace's calls with those of a later execution of the same message.
+    pub fn diff(&self, after: &ExecutionTrace) -> TraceDiff {
+        TraceDiff::of_traces(self, after)
+    }
+}
+
+// Aligns two sequences of sibling calls by their longest common subsequence of
+// (from, to, method), and compares each aligned pair.
+fn diff_calls(before: &[Invocation], after: &[Invocation]) -> Vec<CallDiff> {
+    let key = |inv: &Invocation| (inv.call.from, inv.call.to, inv.call.method);
+    // lcs[i][j] is the length of the longest common subsequence of before[i..] and after[j..].
+    let mut lcs = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            lcs[i][j] = if key(&before[i]) == key(&after[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut diffs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < before.len() || j < after.len() {
+        if i < before.len() && j < after.len() && key(&before[i]) == key(&after[j]) {
+            diffs.push(diff_matched(&before[i], &after[j]));
+            i += 1;
+            j += 1;
+        } else if j < after.len() && (i == before.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            diffs.push(unmatched(&after[j], DiffKind::Added));
+            j += 1;
+        } else {
+            diffs.push(unmatched(&before[i], DiffKind::Removed));
+            i += 1;
+        }
+    }
+    diffs
+}
+
+fn diff_matched(before_inv: &Invocation, after_inv: &Invocation) -> CallDiff {
+    let (before, after) = (&before_inv.call, &after_inv.call);
+    let mut changes = Vec::new();
+    if before.value != after.value {
+        changes
+            .push(CallChange::Value { before: before.value.clone(), after: after.value.clone() });
+    }
+    if before.params != after.params {
+        changes.push(CallChange::Params {
+            before: before.params.clone(),
+            after: after.params.clone(),
+        });
+    }
+    match (&before.outcome, &after.outcome) {
+        (
+            CallOutcome::Return { exit_code: code_before, return_value: ret_before },
+            CallOutcome::Return { exit_code: code_after, return_value: ret_after },
+        ) if code_before == code_after => {
+            if ret_before != ret_after {
+                changes.push(CallChange::ReturnValue {
+                    before: ret_before.clone(),
+                    after: ret_after.clone(),
+                });
+            }
+        }
+        (outcome_before, outcome_after) => {
+            if outcome_before != outcome_after {
+                changes.push(CallChange::Outcome {
+                    before: outcome_before.clone(),
+                    after: outcome_after.clone(),
+                });
+            }
+        }
+    }
+    CallDiff {
+        from: after.from,
+        to: after.to,
+        method: after.method,
+        code: after.code.or(before.code),
+        kind: DiffKind::Matched,
+        changes,
+        gas_before: before.gas,
+        gas_after: after.gas,
+        subcalls: diff_calls(&before_inv.subinvocations, &after_inv.subinvocations),
+    }
+}
+
+// A call present in only one execution, with its subcalls likewise.
+fn unmatched(inv: &Invocation, kind: DiffKind) -> CallDiff {
+    let call = &inv.call;
+    let (gas_before, gas_after) = match kind {
+        DiffKind::Removed => (call.gas, GasCharge::zero()),
+        _ => (GasCharge::zero(), call.gas),
+    };
+    CallDiff {
+        from: call.from,
+        to: call.to,
+        method: call.method,
+        code: call.code,
+        kind,
+        changes: Vec::new(),
+        gas_before,
+        gas_after,
+        subcalls: inv.subinvocations.iter().map(|sub| unmatched(sub, kind)).collect(),
+    }
+}
+
+// Represents an invocation trace as an invocation without code or gas.
+// Invocation traces have no events, so the calls' event indices are zero.
+fn invocation_from_trace(trace: &InvocationTrace) -> Invocation {
+    fn convert(
+        trace: &InvocationTrace,
+        depth: usize,
+        parent: Option<usize>,
+        index: &mut usize,
+    ) -> Invocation {
+        let outcome = match trace.error_number {
+            Some(errno) => CallOutcome::Error { errno, reason: String::new() },
+            None => CallOutcome::Return {
+                exit_code: trace.exit_code,
+                return_value: trace.return_value.clone(),
+            },
+        };
+        let call = CallRecord {
+            index: *index,
+            depth,
+            parent,
+            call_event: 0,
+            return_event: Some(0),
+            from: trace.from,
+            to: trace.to,
+            method: trace.method,
+            params: trace.params.clone(),
+            value: trace.value.clone(),
+            code: None,
+            outcome,
+            self_gas: GasCharge::zero(),
+            gas: GasCharge::zero(),
+        };
+        *index += 1;
+        let subinvocations = trace
+            .subinvocations
+            .iter()
+            .map(|sub| convert(sub, depth + 1, Some(call.index), index))
+            .collect();
+        Invocation { call, subinvocations }
+    }
+    convert(trace, 0, None, &mut 0)
+}
+
+fn format_outcome(outcome: &CallOutcome) -> String {
+    match outcome {
+        CallOutcome::Return { exit_code, .. } => format!("exit_code={}", exit_code),
+        CallOutcome::Error { errno, reason } if reason.is_empty() => format!("error={}", errno),
+        CallOutcome::Error { errno, reason } => format!("error={} ({})", errno, reason),
+        CallOutcome::Unfinished => "unfinished".to_string(),
+    }
+}
+
+fn format_gas(milli: u64) -> String {
+    milli_to_gas(milli).to_formatted_string(&Locale::en)
+}
+
+fn format_delta(milli: i64) -> String {
+    let sign = if milli < 0 { "-" } else { "+" };
+    format!("{}{}", sign, format_gas(milli.unsigned_abs()))
+}
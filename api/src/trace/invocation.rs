@@ -0,0 +1,54 @@
+use crate::trace::query::CallRecord;
+use crate::trace::{ExecutionTrace, TraceConversionError};
+
+/// A call and its nested calls, reconstructed from an execution trace.
+/// Unlike the VM-independent InvocationTrace, this keeps the detail of syscall errors,
+/// the code of the receiving actor, and the gas charged.
+#[derive(Clone, Debug)]
+pub struct Invocation {
+    /// The call, matched with its return or error.
+    pub call: CallRecord,
+    pub subinvocations: Vec<Invocation>,
+}
+
+impl ExecutionTrace {
+    /// Reconstructs the tree of invocations rooted at the message's top-level call.
+    /// Calls that had not returned by the end of the trace are left unfinished.
+    /// Fails if the trace has no calls, more than one top-level call, or a return or error
+    /// that doesn't match any open call.
+    pub fn invocation_tree(&self) -> Result<Invocation, TraceConversionError> {
+        let (calls, unmatched) = self.match_calls();
+        // Report whichever problem comes first in the trace.
+        let second_root = calls.iter().filter(|c| c.depth == 0).nth(1).map(|c| c.call_event);
+        match (unmatched, second_root) {
+            (Some(event), Some(root)) if root < event => {
+                return Err(TraceConversionError::MultipleRoots { event: root })
+            }
+            (Some(event), _) => return Err(TraceConversionError::UnmatchedReturn { event }),
+            (None, Some(event)) => return Err(TraceConversionError::MultipleRoots { event }),
+            (None, None) => {}
+        }
+
+        invocations(calls).into_iter().next().ok_or(TraceConversionError::MissingRoot)
+    }
+}
+
+// Builds the trees of invocations rooted at each top-level call, in the order they were made.
+pub(crate) fn invocations(calls: Vec<CallRecord>) -> Vec<Invocation> {
+    // Build the trees from the innermost and latest calls outwards, so each call's
+    // subinvocations are complete before it is added to its parent.
+    let mut subinvocations: Vec<Vec<Invocation>> = calls.iter().map(|_| Vec::new()).collect();
+    let mut roots = Vec::new();
+    for call in calls.into_iter().rev() {
+        let mut subs = std::mem::take(&mut subinvocations[call.index]);
+        subs.reverse();
+        let parent = call.parent;
+        let invocation = Invocation { call, subinvocations: subs };
+        match parent {
+            Some(parent) => subinvocations[parent].push(invocation),
+            None => roots.push(invocation),
+        }
+    }
+    roots.reverse();
+    roots
+}
@@ -0,0 +1,305 @@
+use cid::Cid;
+use itertools::Itertools;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::time::Duration;
+
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_shared::{ActorID, MethodNum};
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+use vm_api::trace::InvocationTrace;
+
+use crate::events::format_event;
+use crate::names::NameResolver;
+use crate::trace::decode::DecoderRegistry;
+use crate::trace::invocation::Invocation;
+use crate::trace::query::{CallOutcome, CallRecord};
+
+pub mod chrome;
+pub mod decode;
+pub mod diff;
+pub mod invocation;
+pub mod query;
+
+/// A trace of a single message execution comprising a series of events.
+/// An execution trace is easily produced by any abstract VM and can be used for low-level analysis
+/// of gas costs and other call-events.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct ExecutionTrace {
+    events: Vec<ExecutionEvent>,
+}
+
+impl ExecutionTrace {
+    pub fn new(events: Vec<ExecutionEvent>) -> Self {
+        Self { events }
+    }
+
+    pub fn events(&self) -> &[ExecutionEvent] {
+        &self.events
+    }
+
+    /// Returns the trace with only the events describing its call and span structure,
+    /// dropping gas charges, IPLD operations, logs and unrecognised events to save memory.
+    pub fn into_structure(self) -> ExecutionTrace {
+        let events = self
+            .events
+            .into_iter()
+            .filter(|e| {
+                !matches!(
+                    e,
+                    ExecutionEvent::GasCharge { .. }
+                        | ExecutionEvent::Ipld { .. }
+                        | ExecutionEvent::Log { .. }
+                        | ExecutionEvent::Unrecognized { .. }
+                )
+            })
+            .collect();
+        ExecutionTrace { events }
+    }
+
+    pub fn format(&self) -> String {
+        self.events.iter().map(|e| format!("{:?}", e)).join("\n")
+    }
+
+    /// Formats the trace with calls and invoked code described by name, e.g.
+    /// `Call(faucet->Init::Exec) value=0 gas_limit=10000000000` and `InvokeActor(Init)`.
+    pub fn format_with(&self, resolver: &NameResolver) -> String {
+        self.format_events(resolver, None)
+    }
+
+    /// Formats the trace with names as for `format_with`, and with call parameters and return
+    /// values decoded for display.
+    pub fn format_decoded(&self, resolver: &NameResolver, decoders: &DecoderRegistry) -> String {
+        self.format_events(resolver, Some(decoders))
+    }
+
+    fn format_events(&self, resolver: &NameResolver, decoders: Option<&DecoderRegistry>) -> String {
+        let calls = self.calls();
+        let by_call: HashMap<usize, &CallRecord> =
+            calls.iter().map(|c| (c.call_event, c)).collect();
+        let by_return: HashMap<usize, &CallRecord> =
+            calls.iter().filter_map(|c| c.return_event.map(|e| (e, c))).collect();
+        // The receiver's type name, from its code if invoked, else from its address.
+        let type_name = |call: &CallRecord| match &call.code {
+            Some(code) => resolver.code_type(code),
+            None => call.to.id().ok().and_then(|id| resolver.actor_type(id)),
+        };
+        let mut lines = Vec::with_capacity(self.events.len());
+        for (idx, event) in self.events.iter().enumerate() {
+            let line = match event {
+                ExecutionEvent::Call {
+                    from,
+                    to,
+                    method,
+                    params,
+                    value,
+                    gas_limit,
+                    read_only,
+                    ..
+                } => {
+                    let code = by_call.get(&idx).and_then(|c| c.code.as_ref());
+                    let type_name = by_call.get(&idx).and_then(|c| type_name(c));
+                    let mut line = format!(
+                        "{} value={} gas_limit={}{}",
+                        resolver.call_label(*from, to, *method, code),
+                        value,
+                        gas_limit,
+                        if *read_only { " read_only" } else { "" }
+                    );
+                    if let (Some(decoders), Some(params)) = (decoders, params) {
+                        line += &format!(
+                            " params={}",
+                            decoders.format_params(type_name, *method, params)
+                        );
+                    }
+                    line
+                }
+                ExecutionEvent::CallReturn { return_value, exit_code } => {
+                    let (type_name, method) =
+                        by_return.get(&idx).map_or((None, 0), |c| (type_name(c), c.method));
+                    match (decoders, return_value) {
+                        (Some(decoders), Some(ret)) => format!(
+                            "CallReturn exit_code={} return={}",
+                            exit_code,
+                            decoders.format_return(type_name, method, ret)
+                        ),
+                        _ => format!("{:?}", event),
+                    }
+                }
+                ExecutionEvent::InvokeActor { cid } => {
+                    format!("InvokeActor({})", resolver.code_type(cid).unwrap_or(&cid.to_string()))
+                }
+                ExecutionEvent::ActorEvent { emitter, event } => {
+                    format!(
+                        "ActorEvent({}: {})",
+                        resolver.actor_name(*emitter),
+                        format_event(event)
+                    )
+                }
+                e => format!("{:?}", e),
+            };
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+/// An event forming part of an execution trace.
+/// This is closely modelled on the FVM's internal execution event type,
+/// but usable without depending on the FVM directly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum ExecutionEvent {
+    GasCharge {
+        name: Cow<'static, str>,
+        compute_milli: u64,
+        other_milli: u64,
+        /// The wall-clock time taken by the operation charged for, if the VM recorded it.
+        #[cfg_attr(feature = "persistence", serde(default))]
+        elapsed: Option<Duration>,
+    },
+    Call {
+        from: ActorID,
+        to: Address,
+        method: MethodNum,
+        params: Option<IpldBlock>,
+        value: TokenAmount,
+        gas_limit: u64,
+        read_only: bool,
+    },
+    CallReturn {
+        return_value: Option<IpldBlock>,
+        exit_code: ExitCode,
+    },
+    CallError {
+        reason: String,
+        #[cfg_attr(feature = "persistence", serde(with = "crate::persist::errno"))]
+        errno: ErrorNumber,
+    },
+    InvokeActor {
+        cid: Cid,
+    },
+    /// A read or write of an IPLD block by the actor executing at this point.
+    Ipld {
+        op: IpldOperation,
+        cid: Cid,
+        size: usize,
+    },
+    Log {
+        msg: String,
+    },
+    /// An actor event (FIP-0049) emitted by the actor executing at this point.
+    /// Events emitted by a call that later aborted are present here, though the VM discards them.
+    ActorEvent {
+        emitter: ActorID,
+        event: fvm_shared::event::ActorEvent,
+    },
+    /// Opens a named span, delimiting a section of execution for analysis.
+    /// Attributes are key-value pairs describing the span, in the order given.
+    SpanStart {
+        label: String,
+        attrs: Vec<(String, String)>,
+    },
+    /// Closes the innermost open named span with a matching label.
+    SpanEnd {
+        label: String,
+    },
+    /// An event from the VM that the workbench doesn't model, described by the VM.
+    /// This allows traces from newer VM versions to be processed, ignoring such events.
+    Unrecognized {
+        description: String,
+    },
+}
+
+/// An operation on an IPLD block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum IpldOperation {
+    Get,
+    Put,
+}
+
+/// A problem reconstructing the invocations of an execution trace, or converting it to an
+/// invocation trace.
+/// Event indices refer to the position in the trace's events at which the problem was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceConversionError {
+    /// A call return or error that doesn't match any open call.
+    UnmatchedReturn { event: usize },
+    /// A top-level call after the first top-level call returned.
+    MultipleRoots { event: usize },
+    /// A call that had not returned by the end of the trace (the innermost, if several).
+    UnreturnedCall { event: usize },
+    /// A trace with no calls.
+    MissingRoot,
+}
+
+impl Display for TraceConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceConversionError::UnmatchedReturn { event } => {
+                write!(f, "event {}: return with no open call", event)
+            }
+            TraceConversionError::MultipleRoots { event } => {
+                write!(f, "event {}: second top-level call", event)
+            }
+            TraceConversionError::UnreturnedCall { event } => {
+                write!(f, "event {}: call did not return", event)
+            }
+            TraceConversionError::MissingRoot => write!(f, "trace has no calls"),
+        }
+    }
+}
+
+impl std::error::Error for TraceConversionError {}
+
+impl TryFrom<ExecutionTrace> for InvocationTrace {
+    type Error = TraceConversionError;
+
+    fn try_from(e_trace: ExecutionTrace) -> Result<InvocationTrace, TraceConversionError> {
+        InvocationTrace::try_from(&e_trace)
+    }
+}
+
+impl TryFrom<&ExecutionTrace> for InvocationTrace {
+    type Error = TraceConversionError;
+
+    fn try_from(e_trace: &ExecutionTrace) -> Result<InvocationTrace, TraceConversionError> {
+        into_invocation_trace(e_trace.invocation_tree()?)
+    }
+}
+
+// Converts an invocation and its subinvocations, failing at the innermost unfinished call.
+fn into_invocation_trace(inv: Invocation) -> Result<InvocationTrace, TraceConversionError> {
+    let subinvocations =
+        inv.subinvocations.into_iter().map(into_invocation_trace).collect::<Result<_, _>>()?;
+    let call = inv.call;
+    let (exit_code, return_value, error_number) = match call.outcome {
+        CallOutcome::Return { exit_code, return_value } => (exit_code, return_value, None),
+        // The invocation trace has no exit code for a syscall error, so records the
+        // error number alongside a placeholder exit code.
+        // The reason is available from the workbench's own invocation tree.
+        CallOutcome::Error { errno, .. } => (ExitCode::SYS_ASSERTION_FAILED, None, Some(errno)),
+        CallOutcome::Unfinished => {
+            return Err(TraceConversionError::UnreturnedCall { event: call.call_event })
+        }
+    };
+    Ok(InvocationTrace {
+        from: call.from,
+        to: call.to,
+        method: call.method,
+        params: call.params,
+        value: call.value,
+        exit_code,
+        return_value,
+        subinvocations,
+        error_number,
+    })
+}
@@ -0,0 +1,442 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use cid::Cid;
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_shared::address::Address;
+use fvm_shared::bigint::BigInt;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_shared::{ActorID, MethodNum};
+use vm_api::trace::InvocationTrace;
+
+use crate::analysis::GasCharge;
+use crate::trace::{ExecutionEvent, ExecutionTrace};
+
+/// A call in an execution trace, matched with its return or error.
+#[derive(Clone, Debug)]
+pub struct CallRecord {
+    /// The position of this call among all calls in the trace, in the order they were made.
+    pub index: usize,
+    /// The nesting depth of the call, zero for the message's top-level call.
+    pub depth: usize,
+    /// The index of the calling call, if any.
+    pub parent: Option<usize>,
+    /// The index in the trace's events of the call.
+    pub call_event: usize,
+    /// The index in the trace's events of the call's return or error, if any.
+    pub return_event: Option<usize>,
+    pub from: ActorID,
+    pub to: Address,
+    pub method: MethodNum,
+    pub params: Option<IpldBlock>,
+    pub value: TokenAmount,
+    /// The code of the receiving actor, if it was invoked.
+    pub code: Option<Cid>,
+    pub outcome: CallOutcome,
+    /// Gas charged directly by this call, excluding by nested calls.
+    pub self_gas: GasCharge,
+    /// Gas charged between the call and its return, including by nested calls.
+    pub gas: GasCharge,
+}
+
+/// How a call ended.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallOutcome {
+    /// The receiver returned, successfully or otherwise.
+    Return { exit_code: ExitCode, return_value: Option<IpldBlock> },
+    /// The call failed with a syscall error before or without the receiver returning.
+    Error { errno: ErrorNumber, reason: String },
+    /// The trace ended before the call returned.
+    Unfinished,
+}
+
+impl CallRecord {
+    /// The exit code with which the call returned, if it returned.
+    pub fn exit_code(&self) -> Option<ExitCode> {
+        match &self.outcome {
+            CallOutcome::Return { exit_code, .. } => Some(*exit_code),
+            _ => None,
+        }
+    }
+
+    /// The error number and reason with which the call failed, if it failed with an error.
+    pub fn error(&self) -> Option<(ErrorNumber, &str)> {
+        match &self.outcome {
+            CallOutcome::Error { errno, reason } => Some((*errno, reason.as_str())),
+            _ => None,
+        }
+    }
+
+    /// The value returned by the call, if any.
+    pub fn return_value(&self) -> Option<&IpldBlock> {
+        match &self.outcome {
+            CallOutcome::Return { return_value, .. } => return_value.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Whether the call returned a non-success exit code or failed with an error.
+    pub fn is_failure(&self) -> bool {
+        match &self.outcome {
+            CallOutcome::Return { exit_code, .. } => !exit_code.is_success(),
+            CallOutcome::Error { .. } => true,
+            CallOutcome::Unfinished => false,
+        }
+    }
+}
+
+impl ExecutionTrace {
+    /// Returns all calls in the trace, in the order they were made.
+    /// Returns and errors that don't match any open call are ignored.
+    pub fn calls(&self) -> Vec<CallRecord> {
+        self.match_calls().0
+    }
+
+    // Matches each call with its return or error as for `calls`, also returning the index of
+    // the first return or error that matched no open call, if any.
+    // This is the one reconstruction of the trace's calls, on which the others are built.
+    pub(crate) fn match_calls(&self) -> (Vec<CallRecord>, Option<usize>) {
+        let mut calls: Vec<CallRecord> = Vec::new();
+        let mut unmatched = None;
+        // Indices of calls that are yet to return, innermost last.
+        let mut open: Vec<usize> = Vec::new();
+        for (event_idx, event) in self.events().iter().enumerate() {
+            let outcome = match event {
+                ExecutionEvent::GasCharge { compute_milli, other_milli, .. } => {
+                    let charge = GasCharge::new_millis(*compute_milli, *other_milli);
+                    for idx in &open {
+                        calls[*idx].gas += charge;
+                    }
+                    if let Some(idx) = open.last() {
+                        calls[*idx].self_gas += charge;
+                    }
+                    continue;
+                }
+                ExecutionEvent::Call { from, to, method, params, value, .. } => {
+                    open.push(calls.len());
+                    calls.push(CallRecord {
+                        index: calls.len(),
+                        depth: open.len() - 1,
+                        parent: open.len().checked_sub(2).map(|i| open[i]),
+                        call_event: event_idx,
+                        return_event: None,
+                        from: *from,
+                        to: *to,
+                        method: *method,
+                        params: params.clone(),
+                        value: value.clone(),
+                        code: None,
+                        outcome: CallOutcome::Unfinished,
+                        self_gas: GasCharge::zero(),
+                        gas: GasCharge::zero(),
+                    });
+                    continue;
+                }
+                ExecutionEvent::InvokeActor { cid } => {
+                    if let Some(idx) = open.last() {
+                        calls[*idx].code.get_or_insert(*cid);
+                    }
+                    continue;
+                }
+                ExecutionEvent::CallReturn { return_value, exit_code } => CallOutcome::Return {
+                    exit_code: *exit_code,
+                    return_value: return_value.clone(),
+                },
+                ExecutionEvent::CallError { reason, errno } => {
+                    CallOutcome::Error { errno: *errno, reason: reason.clone() }
+                }
+                _ => continue,
+            };
+            match open.pop() {
+                Some(idx) => {
+                    calls[idx].return_event = Some(event_idx);
+                    calls[idx].outcome = outcome;
+                }
+                None => {
+                    unmatched.get_or_insert(event_idx);
+                }
+            }
+        }
+        (calls, unmatched)
+    }
+
+    /// Returns the calls matching a filter, in the order they were made.
+    pub fn find_calls(&self, filter: &CallFilter) -> Vec<CallRecord> {
+        self.calls().into_iter().filter(|c| filter.matches(c)).collect()
+    }
+
+    /// Returns the failed call that returned first.
+    /// This is the origin of the first failure, which may have propagated to its callers.
+    pub fn first_failing_call(&self) -> Option<CallRecord> {
+        self.calls().into_iter().filter(|c| c.is_failure()).min_by_key(|c| c.return_event)
+    }
+
+    /// Returns the first of the most deeply nested calls.
+    pub fn deepest_call(&self) -> Option<CallRecord> {
+        self.calls().into_iter().rev().max_by_key(|c| c.depth)
+    }
+}
+
+/// Returns an invocation and all its subinvocations with their depths, in the order they
+/// were made.
+pub fn flatten_invocations(invocation: &InvocationTrace) -> Vec<(usize, &InvocationTrace)> {
+    fn visit<'a>(
+        inv: &'a InvocationTrace,
+        depth: usize,
+        out: &mut Vec<(usize, &'a InvocationTrace)>,
+    ) {
+        out.push((depth, inv));
+        for sub in &inv.subinvocations {
+            visit(sub, depth + 1, out);
+        }
+    }
+    let mut out = Vec::new();
+    visit(invocation, 0, &mut out);
+    out
+}
+
+/// Returns the failed invocation within an invocation tree that returned first, with its depth.
+/// This is the origin of the first failure, which may have propagated to its callers.
+pub fn first_failing_invocation(invocation: &InvocationTrace) -> Option<(usize, &InvocationTrace)> {
+    // Invocations return after their subinvocations, so the first to return is the first
+    // in post-order.
+    fn visit(inv: &InvocationTrace, depth: usize) -> Option<(usize, &InvocationTrace)> {
+        inv.subinvocations.iter().find_map(|sub| visit(sub, depth + 1)).or_else(|| {
+            let failed = inv.error_number.is_some() || !inv.exit_code.is_success();
+            failed.then_some((depth, inv))
+        })
+    }
+    visit(invocation, 0)
+}
+
+/// Returns the first of the most deeply nested invocations within an invocation tree,
+/// with its depth.
+pub fn deepest_invocation(invocation: &InvocationTrace) -> (usize, &InvocationTrace) {
+    flatten_invocations(invocation).into_iter().rev().max_by_key(|(depth, _)| *depth).unwrap()
+}
+
+/// Returns the invocations within an invocation tree that match a filter, with their depths.
+pub fn find_invocations<'a>(
+    invocation: &'a InvocationTrace,
+    filter: &CallFilter,
+) -> Vec<(usize, &'a InvocationTrace)> {
+    flatten_invocations(invocation)
+        .into_iter()
+        .filter(|(depth, inv)| {
+            let failed = inv.error_number.is_some() || !inv.exit_code.is_success();
+            let exit_code = if inv.error_number.is_some() { None } else { Some(inv.exit_code) };
+            filter
+                .matches_parts(inv.from, &inv.to, inv.method, exit_code, failed, *depth, &inv.value)
+        })
+        .collect()
+}
+
+/// A conjunction of conditions on calls.
+/// Filters can be built with methods, or parsed from text like
+/// `from=100 to=f04 method=2 exit!=0 depth>=1 value>0`.
+/// Fields are `from` (actor ID or ID address), `to` (address), `method`, `exit` (exit code),
+/// `depth`, `value` (attoFIL) and `failed` (`true` or `false`).
+/// Operators are `=`, `!=`, `<`, `<=`, `>` and `>=`.
+/// A call that failed with an error, rather than returning, has no exit code, and so satisfies
+/// only `!=` conditions on it.
+#[derive(Clone, Debug, Default)]
+pub struct CallFilter {
+    conditions: Vec<Condition>,
+}
+
+/// A comparison of a call's field with some value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Cmp {
+    fn test<T: PartialOrd>(self, actual: &T, expected: &T) -> bool {
+        match self {
+            Cmp::Eq => actual == expected,
+            Cmp::Ne => actual != expected,
+            Cmp::Lt => actual < expected,
+            Cmp::Le => actual <= expected,
+            Cmp::Gt => actual > expected,
+            Cmp::Ge => actual >= expected,
+        }
+    }
+}
+
+impl Display for Cmp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            Cmp::Eq => "=",
+            Cmp::Ne => "!=",
+            Cmp::Lt => "<",
+            Cmp::Le => "<=",
+            Cmp::Gt => ">",
+            Cmp::Ge => ">=",
+        };
+        f.write_str(op)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Condition {
+    From(Cmp, ActorID),
+    To(Cmp, Address),
+    Method(Cmp, MethodNum),
+    ExitCode(Cmp, u32),
+    Depth(Cmp, usize),
+    Value(Cmp, TokenAmount),
+    Failed(bool),
+}
+
+impl CallFilter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Matches calls from an actor.
+    pub fn from(self, from: ActorID) -> Self {
+        self.with(Condition::From(Cmp::Eq, from))
+    }
+
+    /// Matches calls to an address.
+    pub fn to(self, to: Address) -> Self {
+        self.with(Condition::To(Cmp::Eq, to))
+    }
+
+    /// Matches calls to a method.
+    pub fn method(self, method: MethodNum) -> Self {
+        self.with(Condition::Method(Cmp::Eq, method))
+    }
+
+    /// Matches calls that returned an exit code.
+    pub fn exit_code(self, exit_code: ExitCode) -> Self {
+        self.with(Condition::ExitCode(Cmp::Eq, exit_code.value()))
+    }
+
+    /// Matches calls that returned a non-success exit code or failed with an error.
+    pub fn failed(self) -> Self {
+        self.with(Condition::Failed(true))
+    }
+
+    /// Matches calls at a comparable nesting depth.
+    pub fn depth(self, cmp: Cmp, depth: usize) -> Self {
+        self.with(Condition::Depth(cmp, depth))
+    }
+
+    /// Matches calls transferring a comparable value.
+    pub fn value(self, cmp: Cmp, value: TokenAmount) -> Self {
+        self.with(Condition::Value(cmp, value))
+    }
+
+    /// Returns whether a call satisfies every condition.
+    pub fn matches(&self, call: &CallRecord) -> bool {
+        self.matches_parts(
+            call.from,
+            &call.to,
+            call.method,
+            call.exit_code(),
+            call.is_failure(),
+            call.depth,
+            &call.value,
+        )
+    }
+
+    fn with(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    // Checks the conditions on fields common to call records and invocation traces.
+    #[allow(clippy::too_many_arguments)]
+    fn matches_parts(
+        &self,
+        from: ActorID,
+        to: &Address,
+        method: MethodNum,
+        exit_code: Option<ExitCode>,
+        failed: bool,
+        depth: usize,
+        value: &TokenAmount,
+    ) -> bool {
+        self.conditions.iter().all(|c| match c {
+            Condition::From(cmp, expected) => cmp.test(&from, expected),
+            Condition::To(cmp, expected) => cmp.test(to, expected),
+            Condition::Method(cmp, expected) => cmp.test(&method, expected),
+            Condition::ExitCode(cmp, expected) => match exit_code {
+                Some(exit_code) => cmp.test(&exit_code.value(), expected),
+                None => *cmp == Cmp::Ne,
+            },
+            Condition::Depth(cmp, expected) => cmp.test(&depth, expected),
+            Condition::Value(cmp, expected) => cmp.test(value, expected),
+            Condition::Failed(expected) => failed == *expected,
+        })
+    }
+}
+
+impl FromStr for CallFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = CallFilter::new();
+        for term in s.split_whitespace() {
+            // Two-character operators first, so that "<=" isn't read as "<".
+            let (field, cmp, value) = [
+                ("!=", Cmp::Ne),
+                ("<=", Cmp::Le),
+                (">=", Cmp::Ge),
+                ("=", Cmp::Eq),
+                ("<", Cmp::Lt),
+                (">", Cmp::Gt),
+            ]
+            .iter()
+            .find_map(|(op, cmp)| term.split_once(op).map(|(f, v)| (f, *cmp, v)))
+            .ok_or_else(|| anyhow!("no comparison in filter term {}", term))?;
+            let condition = match field {
+                "from" => Condition::From(cmp, parse_actor_id(value)?),
+                "to" => Condition::To(cmp, parse_address(value)?),
+                "method" => Condition::Method(cmp, value.parse()?),
+                "exit" => Condition::ExitCode(cmp, value.parse()?),
+                "depth" => Condition::Depth(cmp, value.parse()?),
+                "value" => Condition::Value(
+                    cmp,
+                    TokenAmount::from_atto(
+                        value
+                            .parse::<BigInt>()
+                            .map_err(|e| anyhow!("invalid value {}: {}", value, e))?,
+                    ),
+                ),
+                "failed" => match cmp {
+                    Cmp::Eq => Condition::Failed(value.parse()?),
+                    Cmp::Ne => Condition::Failed(!value.parse::<bool>()?),
+                    _ => bail!("invalid comparison {} for failed", cmp),
+                },
+                _ => bail!("unknown filter field {}", field),
+            };
+            if matches!(condition, Condition::From(..) | Condition::To(..))
+                && !matches!(cmp, Cmp::Eq | Cmp::Ne)
+            {
+                bail!("invalid comparison {} for {}", cmp, field);
+            }
+            filter.conditions.push(condition);
+        }
+        Ok(filter)
+    }
+}
+
+fn parse_address(s: &str) -> anyhow::Result<Address> {
+    match s.parse::<ActorID>() {
+        Ok(id) => Ok(Address::new_id(id)),
+        Err(_) => Address::from_str(s).map_err(|e| anyhow!("invalid address {}: {}", s, e)),
+    }
+}
+
+fn parse_actor_id(s: &str) -> anyhow::Result<ActorID> {
+    parse_address(s)?.id().map_err(|_| anyhow!("not an ID address: {}", s))
+}
@@ -1,5 +1,6 @@
-use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::num::NonZeroU64;
 
 use anyhow::anyhow;
 use cid::Cid;
@@ -11,11 +12,18 @@ use fvm_shared::address::Address;
 use fvm_shared::bigint::Zero;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
+use fvm_shared::event::StampedEvent;
 use fvm_shared::message::Message;
 use fvm_shared::{ActorID, MethodNum, BLOCK_GAS_LIMIT};
+use itertools::Itertools;
 use vm_api::trace::InvocationTrace;
 use vm_api::{vm_err, ActorState, MessageResult, MockPrimitives, Primitives, VMError, VM};
 
+use crate::analysis::assertion::{GasBound, GasMeasure, SpanSelector};
+use crate::analysis::TraceAnalysis;
+use crate::events;
+use crate::names::NameResolver;
+use crate::trace::{ExecutionEvent, TraceConversionError};
 pub use crate::{bench::Bench, trace::ExecutionTrace, ExecutionResult};
 
 pub struct ExecutionWrangler {
@@ -29,7 +37,74 @@ pub struct ExecutionWrangler {
     sequences: RefCell<HashMap<Address, u64>>,
     msg_length: usize,
     compute_msg_length: bool,
-    execution_results: RefCell<Vec<ExecutionTrace>>,
+    execution_results: RefCell<VecDeque<ExecutionTrace>>,
+    retention: TraceRetention,
+    // Traces eligible for retention so far, for sampling.
+    eligible_traces: Cell<u64>,
+    // Whether to keep the next message's trace in full, regardless of the retention policy.
+    trace_next_in_full: Cell<bool>,
+    // Whether the trace of the last message executed was kept, and if so whether in full.
+    last_trace_kept: Cell<Option<bool>>,
+    // Actor events emitted by the last message executed.
+    last_events: RefCell<Vec<StampedEvent>>,
+    // Names of the built-in actor types, for labelling calls in gas assertions.
+    builtin_names: OnceCell<NameResolver>,
+}
+
+/// A policy for which execution traces a wrangler keeps, bounding the memory used by long
+/// simulations. The default keeps every trace in full.
+/// A message's trace is kept if it passes the failure filter and is sampled, and then only the
+/// most recent traces up to the limit are kept.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceRetention {
+    max_traces: Option<usize>,
+    failures_only: bool,
+    sample_every: NonZeroU64,
+    structure_only: bool,
+}
+
+impl Default for TraceRetention {
+    fn default() -> Self {
+        Self {
+            max_traces: None,
+            failures_only: false,
+            sample_every: NonZeroU64::MIN,
+            structure_only: false,
+        }
+    }
+}
+
+impl TraceRetention {
+    /// A policy that keeps every trace in full.
+    pub fn all() -> Self {
+        Default::default()
+    }
+
+    /// Keeps at most the last `n` traces, dropping the oldest.
+    pub fn last(mut self, n: usize) -> Self {
+        self.max_traces = Some(n);
+        self
+    }
+
+    /// Keeps only the traces of messages that exited with a non-zero exit code.
+    pub fn failures_only(mut self) -> Self {
+        self.failures_only = true;
+        self
+    }
+
+    /// Keeps one in every `k` traces that pass the failure filter, starting with the first.
+    pub fn sample(mut self, k: NonZeroU64) -> Self {
+        self.sample_every = k;
+        self
+    }
+
+    /// Keeps only the call structure of traces, dropping gas charges and logs.
+    /// Such traces can still be converted to invocations and queried for calls, but not
+    /// analysed for gas.
+    pub fn structure_only(mut self) -> Self {
+        self.structure_only = true;
+        self
+    }
 }
 
 impl ExecutionWrangler {
@@ -57,10 +132,28 @@ impl ExecutionWrangler {
             sequences: RefCell::new(HashMap::new()),
             msg_length: 0,
             compute_msg_length,
-            execution_results: RefCell::new(Vec::new()),
+            execution_results: RefCell::new(VecDeque::new()),
+            retention: TraceRetention::default(),
+            eligible_traces: Cell::new(0),
+            trace_next_in_full: Cell::new(false),
+            last_trace_kept: Cell::new(None),
+            last_events: RefCell::new(Vec::new()),
+            builtin_names: OnceCell::new(),
         }
     }
 
+    /// Sets which execution traces are kept from subsequent messages.
+    pub fn with_trace_retention(mut self, retention: TraceRetention) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Keeps the trace of the next message executed in full, regardless of the retention policy
+    /// (though it may later be dropped to keep within the limit on the number of traces).
+    pub fn trace_next_message_in_full(&self) {
+        self.trace_next_in_full.set(true);
+    }
+
     /// Creates a new wrangler wrapping a given Bench. The store passed here must be a handle that
     /// operates on the same underlying storage as the bench
     pub fn new_default(
@@ -121,11 +214,120 @@ impl ExecutionWrangler {
         self.bench.borrow().resolve_address(addr)
     }
 
+    /// Returns a resolver naming the built-in actor types, and the code of every actor
+    /// currently in the state tree.
+    /// Register names for particular actors and custom methods with the resolver as needed.
+    pub fn name_resolver(&self) -> NameResolver {
+        let mut resolver = NameResolver::with_builtin_manifest(&self.actor_manifest());
+        for (addr, actor) in self.actor_states() {
+            if let Ok(id) = addr.id() {
+                resolver.register_actor_code(id, actor.code);
+            }
+        }
+        resolver
+    }
+
+    /// Returns copies of the execution traces of all messages executed, in order.
+    /// Only traces kept by the retention policy are included.
+    /// NOTE: These traces will be cleared if take_invocations was called earlier
+    pub fn execution_traces(&self) -> Vec<ExecutionTrace> {
+        self.execution_results.borrow().iter().cloned().collect()
+    }
+
+    /// Takes the invocations of all messages executed, as for VM::take_invocations,
+    /// or returns an error describing the first malformed trace.
+    /// The execution traces are cleared only if all are converted, so on error they remain
+    /// available for inspection.
+    pub fn try_take_invocations(&self) -> Result<Vec<InvocationTrace>, TraceConversionError> {
+        let invocations = self
+            .execution_results
+            .borrow()
+            .iter()
+            .map(InvocationTrace::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.execution_results.borrow_mut().clear();
+        Ok(invocations)
+    }
+
+    /// Returns the actor events emitted by the last message executed, in order.
+    /// Events of calls that aborted are excluded, as they are discarded by the VM.
+    pub fn last_events(&self) -> Vec<StampedEvent> {
+        self.last_events.borrow().clone()
+    }
+
+    /// Returns the actor events of some type (the value of their "$type" entry, like
+    /// "deal-published") emitted by the last message executed, in order.
+    pub fn last_events_of_type(&self, event_type: &str) -> Vec<StampedEvent> {
+        self.last_events
+            .borrow()
+            .iter()
+            .filter(|e| events::event_type(&e.event).as_deref() == Some(event_type))
+            .cloned()
+            .collect()
+    }
+
+    /// Asserts that the last message executed emitted exactly some actor events, in order.
+    pub fn assert_last_events(&self, expected: &[StampedEvent]) {
+        let actual = self.last_events.borrow();
+        if actual.as_slice() != expected {
+            let format = |evs: &[StampedEvent]| {
+                if evs.is_empty() {
+                    return "  (none)".to_string();
+                }
+                evs.iter()
+                    .map(|e| format!("  {}: {}", e.emitter, events::format_event(&e.event)))
+                    .join("\n")
+            };
+            panic!(
+                "unexpected actor events\nexpected:\n{}\nactual:\n{}",
+                format(expected),
+                format(&actual)
+            );
+        }
+    }
+
+    /// Asserts that the gas of spans of the last message executed is within a bound, panicking
+    /// with a report of the spans out of bounds otherwise. Calls are labelled with the names of
+    /// built-in actor types. See also the `assert_span_gas!` macro.
+    /// Panics if the last message's trace was not kept in full, or has no gas charges (as when
+    /// tracing is disabled), since there would be no gas to check.
+    pub fn assert_span_gas(
+        &self,
+        selector: impl Into<SpanSelector>,
+        measure: GasMeasure,
+        bound: GasBound,
+    ) {
+        let trace = self
+            .peek_execution_trace()
+            .filter(|_| self.last_trace_kept.get() == Some(true))
+            .expect("trace of the last message was not kept in full");
+        if !trace.events().iter().any(|e| matches!(e, ExecutionEvent::GasCharge { .. })) {
+            panic!("trace of the last message has no gas charges");
+        }
+        let mut resolver = self
+            .builtin_names
+            .get_or_init(|| NameResolver::with_builtin_manifest(&self.actor_manifest()))
+            .clone();
+        // Invoked calls are labelled by the code invoked, but a receiver that wasn't invoked
+        // is labelled by its current code, if any.
+        for call in trace.calls().iter().filter(|c| c.code.is_none()) {
+            let Ok(id) = call.to.id() else { continue };
+            if let Ok(Some(actor)) = self.find_actor(id) {
+                resolver.register_actor_code(id, actor.code);
+            }
+        }
+        let mut analysis = TraceAnalysis::build(trace).unwrap_or_else(|e| panic!("{}", e));
+        analysis.resolve_names(&resolver);
+        analysis.assert_span_gas(selector, measure, bound);
+    }
+
     /// Returns a copy of the last execution trace if any exist
     /// For test assertions you probably want VM::take_invocations instead
-    /// NOTE: These traces will be cleared if take_invocations was called earlier
+    /// NOTE: These traces will be cleared if take_invocations was called earlier.
+    /// Returns None if the retention policy didn't keep the last message's trace.
     pub fn peek_execution_trace(&self) -> Option<ExecutionTrace> {
-        self.execution_results.borrow().last().cloned()
+        self.last_trace_kept.get()?;
+        self.execution_results.borrow().back().cloned()
     }
 }
 
@@ -169,6 +371,8 @@ impl ExecutionWrangler {
         value: &TokenAmount,
         implicit: bool,
     ) -> Result<MessageResult, VMError> {
+        // The override applies to this message whether or not it executes successfully.
+        let in_full = self.trace_next_in_full.replace(false);
         let raw_params = params.map_or(RawBytes::default(), |block| RawBytes::from(block.data));
         let sequence = *self.sequences.borrow().get(from).unwrap_or(&0);
         let (msg, msg_length) =
@@ -181,12 +385,50 @@ impl ExecutionWrangler {
             self.sequences.borrow_mut().insert(*from, sequence + 1);
         }
         match ret {
-            Ok(res) => {
-                self.execution_results.borrow_mut().push(res.trace.clone());
+            Ok(mut res) => {
+                self.last_events.replace(std::mem::take(&mut res.events));
+                let trace = std::mem::replace(&mut res.trace, ExecutionTrace::new(Vec::new()));
+                let kept = self.retain_trace(trace, res.receipt.exit_code.is_success(), in_full);
+                self.last_trace_kept.set(kept);
                 Ok(res.into())
             }
-            Err(e) => Err(vm_err(&e.to_string())),
+            Err(e) => {
+                self.last_trace_kept.set(None);
+                self.last_events.borrow_mut().clear();
+                Err(vm_err(&e.to_string()))
+            }
+        }
+    }
+
+    // Keeps a message's trace according to the retention policy, or in full if overridden.
+    // Returns whether the trace was kept in full, or None if it wasn't kept.
+    fn retain_trace(&self, trace: ExecutionTrace, success: bool, in_full: bool) -> Option<bool> {
+        let policy = &self.retention;
+        let (trace, full) = if in_full {
+            (trace, true)
+        } else {
+            if policy.failures_only && success {
+                return None;
+            }
+            let eligible = self.eligible_traces.get();
+            self.eligible_traces.set(eligible + 1);
+            if eligible % policy.sample_every.get() != 0 {
+                return None;
+            }
+            if policy.structure_only {
+                (trace.into_structure(), false)
+            } else {
+                (trace, true)
+            }
+        };
+        let mut traces = self.execution_results.borrow_mut();
+        traces.push_back(trace);
+        if let Some(max) = policy.max_traces {
+            while traces.len() > max {
+                traces.pop_front();
+            }
         }
+        (!traces.is_empty()).then_some(full)
     }
 }
 
@@ -247,8 +489,10 @@ impl VM for ExecutionWrangler {
     }
 
     /// Note: this is derived from the underlying ExecutionTraces, so it will clear those when taken
+    /// Panics if a trace is malformed, as the VM trait has no way to report it, leaving the
+    /// traces in place. Use try_take_invocations to handle that case.
     fn take_invocations(&self) -> Vec<InvocationTrace> {
-        self.execution_results.take().into_iter().map(InvocationTrace::from).collect()
+        self.try_take_invocations().unwrap_or_else(|e| panic!("malformed execution trace: {}", e))
     }
 
     fn primitives(&self) -> &dyn Primitives {
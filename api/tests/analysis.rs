@@ -0,0 +1,750 @@
+use std::time::Duration;
+
+use cid::Cid;
+use fvm_shared::address::Address;
+use fvm_workbench_api::analysis::aggregate::{AggregateAnalysis, GasStats};
+use fvm_workbench_api::analysis::assertion::{GasBound, GasMeasure, SpanSelector};
+use fvm_workbench_api::analysis::attribution::ActorGasAttribution;
+use fvm_workbench_api::analysis::baseline::{
+    CompareOptions, GasBaseline, Thresholds, UPDATE_BASELINES_VAR,
+};
+use fvm_workbench_api::analysis::category::{GasCategories, GasCategory};
+use fvm_workbench_api::analysis::html::HtmlReport;
+use fvm_workbench_api::analysis::table::{SpanColumn, SpanOrder, TableOptions};
+use fvm_workbench_api::analysis::timing::ChargeTiming;
+use fvm_workbench_api::analysis::{
+    AnalysisError, AnalysisMode, GasCharge, GasFormat, SpanKind, TraceAnalysis,
+};
+use fvm_workbench_api::assert_span_gas;
+use fvm_workbench_api::names::{frc42_method_number, NameResolver};
+use fvm_workbench_api::trace::decode::DecoderRegistry;
+use fvm_workbench_api::trace::ExecutionEvent::{GasCharge as Charge, InvokeActor};
+use fvm_workbench_api::trace::ExecutionTrace;
+use serde_json::json;
+
+use crate::common::{call, call_to, charge, code, ret, span_end, span_start};
+
+mod common;
+
+#[test]
+fn spans_form_tree() {
+    let trace = ExecutionTrace::new(vec![
+        charge("OnChainMessage", 1000),
+        call(100, 6, 4),
+        charge("wasm_exec", 2000),
+        span_start("load_deals"),
+        charge("ipld_get", 3000),
+        call(6, 5, 7),
+        charge("wasm_exec", 4000),
+        ret(),
+        span_end("load_deals"),
+        charge("wasm_exec", 500),
+        ret(),
+    ]);
+    let analysis = TraceAnalysis::build(trace).unwrap();
+
+    let root = analysis.root();
+    assert_eq!(SpanKind::Root, root.kind());
+    assert_eq!(GasCharge::new_millis(1000, 0), root.self_gas());
+    assert_eq!(GasCharge::new_millis(10_500, 0), analysis.total_gas(root));
+
+    let miner_call = analysis.children(root).collect::<Vec<_>>();
+    assert_eq!(1, miner_call.len());
+    assert_eq!("Call(100->f06::4)", miner_call[0].label());
+    assert_eq!(GasCharge::new_millis(2500, 0), miner_call[0].self_gas());
+
+    let load_deals = analysis.find_by_label("load_deals").collect::<Vec<_>>();
+    assert_eq!(1, load_deals.len());
+    assert_eq!(SpanKind::Named, load_deals[0].kind());
+    assert_eq!(miner_call[0].index(), analysis.parent(load_deals[0]).unwrap().index());
+    assert_eq!(GasCharge::new_millis(3000, 0), load_deals[0].self_gas());
+    assert_eq!(GasCharge::new_millis(7000, 0), analysis.total_gas(load_deals[0]));
+
+    let power_call = analysis.children(load_deals[0]).collect::<Vec<_>>();
+    assert_eq!(1, power_call.len());
+    assert_eq!("Call(6->f05::7)", power_call[0].label());
+    assert_eq!(
+        GasCharge::new_millis(4000, 0),
+        analysis.total_gas_by_name(power_call[0])["wasm_exec"]
+    );
+}
+
+#[test]
+fn malformed_spans_rejected_by_strict_analysis() {
+    let unmatched = ExecutionTrace::new(vec![call(100, 6, 4), span_end("nope"), ret()]);
+    assert_eq!(
+        AnalysisError::UnmatchedSpanEnd { event: 1, label: "nope".to_string() },
+        TraceAnalysis::build(unmatched).err().unwrap()
+    );
+
+    let unclosed = ExecutionTrace::new(vec![call(100, 6, 4), span_start("unclosed"), ret()]);
+    assert_eq!(
+        AnalysisError::UnclosedSpan { event: 2, span: "2-Span(unclosed)".to_string() },
+        TraceAnalysis::build(unclosed).err().unwrap()
+    );
+
+    // A span can't be ended from within a call it made.
+    let across_calls = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        span_start("a"),
+        call(6, 5, 7),
+        span_end("a"),
+        ret(),
+        span_end("a"),
+        ret(),
+    ]);
+    assert_eq!(
+        AnalysisError::UnmatchedSpanEnd { event: 3, label: "a".to_string() },
+        TraceAnalysis::build(across_calls.clone()).err().unwrap()
+    );
+    let analysis = TraceAnalysis::build_lenient(across_calls);
+    assert_eq!(
+        vec![AnalysisError::UnmatchedSpanEnd { event: 3, label: "a".to_string() }],
+        analysis.warnings()
+    );
+    let a = analysis.find_by_label("a").next().unwrap();
+    assert_eq!(vec!["3-Call(6->f05::7)"], analysis.children(a).map(|s| s.id()).collect::<Vec<_>>());
+
+    let overlapping = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        span_start("a"),
+        span_start("b"),
+        span_end("a"),
+        charge("wasm_exec", 1000),
+        span_end("b"),
+        ret(),
+    ]);
+    let overlap = AnalysisError::OverlappingSpan {
+        event: 3,
+        span: "3-Span(b)".to_string(),
+        ended: "2-Span(a)".to_string(),
+    };
+    assert_eq!(overlap, TraceAnalysis::build(overlapping.clone()).err().unwrap());
+    // Leniently, the inner span remains open after the outer one ends.
+    let analysis = TraceAnalysis::build_lenient(overlapping);
+    assert_eq!(vec![overlap], analysis.warnings());
+    assert_eq!(1000, analysis.find_by_label("b").next().unwrap().self_gas().total_milli());
+}
+
+#[test]
+fn lenient_analysis_closes_unclosed_spans() {
+    let trace = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        span_start("unclosed"),
+        charge("wasm_exec", 2000),
+        span_end("nope"),
+        ret(),
+        charge("OnChainReturnValue", 1000),
+        ret(),
+        call(100, 7, 2),
+    ]);
+    let analysis = TraceAnalysis::build_lenient(trace);
+
+    let unclosed = analysis.find_by_label("unclosed").next().unwrap();
+    assert_eq!(GasCharge::new_millis(2000, 0), unclosed.self_gas());
+    assert_eq!(GasCharge::new_millis(1000, 0), analysis.root().self_gas());
+    assert_eq!(
+        vec![
+            AnalysisError::UnmatchedSpanEnd { event: 3, label: "nope".to_string() },
+            AnalysisError::UnclosedSpan { event: 4, span: "2-Span(unclosed)".to_string() },
+            AnalysisError::UnmatchedCallReturn { event: 6 },
+            AnalysisError::UnclosedSpan { event: 8, span: "3-Call(100->f07::2)".to_string() },
+        ],
+        analysis.warnings()
+    );
+}
+
+#[test]
+fn gas_dimensions_kept_through_aggregation() {
+    let trace = ExecutionTrace::new(vec![
+        Charge { name: "ipld_put".into(), compute_milli: 1000, other_milli: 5000, elapsed: None },
+        call(100, 6, 4),
+        Charge { name: "ipld_put".into(), compute_milli: 2000, other_milli: 7000, elapsed: None },
+        ret(),
+    ]);
+    let analysis = TraceAnalysis::build(trace).unwrap();
+    let total = analysis.total_gas(analysis.root());
+    assert_eq!((3000, 12_000), (total.compute_milli(), total.other_milli()));
+    assert_eq!(
+        "Span[0-Root, self: {sum=6 (compute=1, other=5), ipld_put=6 (compute=1, other=5)}, \
+         total: {sum=15 (compute=3, other=12), ipld_put=15 (compute=3, other=12)}]",
+        analysis.format_span_with(analysis.root(), GasFormat::Dimensions)
+    );
+}
+
+#[test]
+fn spans_exported_as_flame_graph_stacks() {
+    let trace = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        charge("wasm_exec", 1000),
+        span_start("load;deals"),
+        charge("ipld_get", 3000),
+        span_end("load;deals"),
+        call(6, 5, 7),
+        charge("wasm_exec", 500),
+        ret(),
+        charge("wasm_exec", 2000),
+        ret(),
+    ]);
+    let analysis = TraceAnalysis::build(trace).unwrap();
+
+    // Stacks are in the order spans were opened, and ';' in labels is replaced.
+    assert_eq!(
+        "Root;Call(100->f06::4);wasm_exec 3000
+Root;Call(100->f06::4);Span(load,deals);ipld_get 3000
+Root;Call(100->f06::4);Call(6->f05::7);wasm_exec 500",
+        analysis.format_folded_stacks()
+    );
+
+    let profile = analysis.to_speedscope("publish_deals");
+    let frames = profile["shared"]["frames"].as_array().unwrap();
+    assert_eq!(
+        vec![
+            "Root",
+            "Call(100->f06::4)",
+            "wasm_exec",
+            "Span(load,deals)",
+            "ipld_get",
+            "Call(6->f05::7)"
+        ],
+        frames.iter().map(|f| f["name"].as_str().unwrap()).collect::<Vec<_>>()
+    );
+    assert_eq!(1, profile["profiles"].as_array().unwrap().len());
+    assert_eq!(
+        json!({
+            "type": "sampled",
+            "name": "publish_deals",
+            "unit": "none",
+            "startValue": 0,
+            "endValue": 6500,
+            "samples": [[0, 1, 2], [0, 1, 3, 4], [0, 1, 5, 2]],
+            "weights": [3000, 3000, 500],
+        }),
+        profile["profiles"][0]
+    );
+}
+
+#[test]
+fn gas_compared_against_baseline() {
+    let before = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        charge("wasm_exec", 10_000),
+        span_start("load_deals"),
+        charge("ipld_get", 5_000),
+        span_end("load_deals"),
+        call(6, 5, 7),
+        charge("wasm_exec", 1_000),
+        ret(),
+        ret(),
+    ]);
+    let after = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        charge("wasm_exec", 10_000),
+        span_start("load_deals"),
+        charge("ipld_get", 6_000),
+        span_end("load_deals"),
+        span_start("verify"),
+        charge("wasm_exec", 200),
+        span_end("verify"),
+        ret(),
+    ]);
+    let before = TraceAnalysis::build(before).unwrap();
+    let after = TraceAnalysis::build(after).unwrap();
+    let baseline =
+        GasBaseline::from_json(&GasBaseline::from_analysis(&before).to_json().unwrap()).unwrap();
+    assert_eq!(GasBaseline::from_analysis(&before), baseline);
+    assert!(baseline.compare(&before, &CompareOptions::default()).is_unchanged());
+
+    // The total of the root and call are within the thresholds, but their ipld_get charges
+    // are not. The span's own increase is within its more lenient thresholds.
+    let options = CompareOptions::new(Thresholds { absolute_milli: 500, relative: 0.05 })
+        .with_override("Span(load_deals)", Thresholds { absolute_milli: 2_000, relative: 0.5 });
+    let comparison = baseline.compare(&after, &options);
+    assert!(comparison.has_regressions());
+    assert!(!comparison.is_unchanged());
+    assert_eq!(
+        vec!["Root", "Root;Call(100->f06::4)"],
+        comparison.regressions().map(|d| d.key.as_str()).collect::<Vec<_>>()
+    );
+    assert_eq!(vec!["Root;Call(100->f06::4);Span(verify)"], comparison.added);
+    assert_eq!(vec!["Root;Call(100->f06::4);Call(6->f05::7)"], comparison.removed);
+    assert_eq!(
+        "Gas regressions: 2
+  Root: total 16,000 -> 16,200 (+200, +1.25%), self 0 -> 0
+    ! ipld_get: 5,000 -> 6,000 (+1,000, +20.00%)
+    wasm_exec: 11,000 -> 10,200 (-800, -7.27%)
+  Root;Call(100->f06::4): total 16,000 -> 16,200 (+200, +1.25%), self 10,000 -> 10,000
+    ! ipld_get: 5,000 -> 6,000 (+1,000, +20.00%)
+    wasm_exec: 11,000 -> 10,200 (-800, -7.27%)
+Other changes: 1
+  Root;Call(100->f06::4);Span(load_deals): total 5,000 -> 6,000 (+1,000, +20.00%), \
+self 5,000 -> 6,000
+    ipld_get: 5,000 -> 6,000 (+1,000, +20.00%)
+Added spans: 1
+  Root;Call(100->f06::4);Span(verify)
+Removed spans: 1
+  Root;Call(100->f06::4);Call(6->f05::7)",
+        comparison.format_report()
+    );
+
+    // A saved baseline must be recorded deliberately, and is compared against thereafter.
+    let path = std::env::temp_dir()
+        .join(format!("fvm-workbench-{}", std::process::id()))
+        .join("baseline.json");
+    let err = GasBaseline::check_saved(&before, &path, &options).unwrap_err();
+    assert!(err.to_string().contains(UPDATE_BASELINES_VAR), "{}", err);
+    assert!(!path.exists());
+    std::env::set_var(UPDATE_BASELINES_VAR, "1");
+    let recorded = GasBaseline::check_saved(&before, &path, &options);
+    std::env::remove_var(UPDATE_BASELINES_VAR);
+    assert!(recorded.unwrap().is_unchanged());
+    assert_eq!(baseline, GasBaseline::load(&path).unwrap());
+    assert!(GasBaseline::check_saved(&after, &path, &options).unwrap().has_regressions());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn calls_labelled_with_resolved_names() {
+    let code = Cid::default();
+    let trace = ExecutionTrace::new(vec![
+        call(100, 1000, 2),
+        InvokeActor { cid: code },
+        call(1000, 6, frc42_method_number("Transfer")),
+        ret(),
+        ret(),
+    ]);
+    let mut resolver = NameResolver::new();
+    resolver.register_actor_name(100, "alice");
+    resolver.register_code_type(code, "Token");
+    resolver.register_method("Token", 2, "Mint");
+    resolver.register_actor_code(6, code);
+    resolver.register_exported_method("Token", "Transfer");
+
+    let mut analysis = TraceAnalysis::build(trace).unwrap();
+    analysis.resolve_names(&resolver);
+    let labels = analysis.spans().iter().map(|s| s.label()).collect::<Vec<_>>();
+    assert_eq!(vec!["Root", "Call(alice->f01000::Mint)", "Call(f01000->f06::Transfer)"], labels);
+}
+
+#[test]
+fn gas_attributed_to_running_actor_code() {
+    let (token, wallet) = (code(b"token"), code(b"wallet"));
+    let trace = ExecutionTrace::new(vec![
+        charge("OnChainMessage", 1000),
+        call(100, 1000, 2),
+        charge("OnValueTransfer", 1000),
+        InvokeActor { cid: wallet },
+        charge("wasm_exec", 3000),
+        call(1000, 6, 4),
+        InvokeActor { cid: token },
+        charge("wasm_exec", 5000),
+        ret(),
+        ret(),
+    ]);
+    let mut attribution = ActorGasAttribution::from_trace(&trace);
+    attribution.merge(&ActorGasAttribution::from_trace(&trace));
+    // Only the message inclusion is unattributed. The value transfer is attributed to the
+    // wallet receiving it.
+    assert_eq!(GasCharge::new_millis(2000, 0), attribution.unattributed());
+    assert_eq!(Some(&GasCharge::new_millis(8000, 0)), attribution.by_code().get(&wallet));
+    assert_eq!(Some(&GasCharge::new_millis(10_000, 0)), attribution.by_code().get(&token));
+
+    let mut resolver = NameResolver::new();
+    resolver.register_code_type(token, "Token");
+    resolver.register_code_type(wallet, "Wallet");
+    assert_eq!(
+        "Token: 50.0%, Wallet: 40.0%, (unattributed): 10.0%",
+        attribution.format_shares(&resolver)
+    );
+}
+
+#[test]
+fn spans_aggregated_by_name_across_messages() {
+    let traces: Vec<ExecutionTrace> = (1..=4)
+        .map(|n| {
+            ExecutionTrace::new(vec![
+                call(100, 6, 4),
+                span_start("load_deals"),
+                charge("ipld_get", 1000 * n),
+                span_end("load_deals"),
+                span_start("load_deals"),
+                charge("ipld_get", 1000),
+                span_end("load_deals"),
+                ret(),
+            ])
+        })
+        .collect();
+    let aggregate = AggregateAnalysis::from_traces(&traces, AnalysisMode::Strict).unwrap();
+    assert_eq!(4, aggregate.messages());
+    assert!(aggregate.warnings().is_empty());
+    assert_eq!(
+        GasStats {
+            count: 8,
+            min: 1000,
+            max: 4000,
+            mean: 1750,
+            p50: 1000,
+            p95: 4000,
+            total: 14_000
+        },
+        aggregate.span_stats("Span(load_deals)").unwrap()
+    );
+    let per_message = aggregate.charge_stats("ipld_get").unwrap();
+    assert_eq!(
+        (4, 2000, 5000, 3000),
+        (per_message.count, per_message.min, per_message.max, per_message.p50)
+    );
+
+    // A malformed trace fails a strict aggregation, but is aggregated leniently with warnings.
+    let malformed = ExecutionTrace::new(vec![call(100, 6, 4), span_end("nope"), ret()]);
+    let traces = [&traces[0], &malformed];
+    assert_eq!(
+        Some(AnalysisError::UnmatchedSpanEnd { event: 1, label: "nope".to_string() }),
+        AggregateAnalysis::from_traces(traces, AnalysisMode::Strict).err()
+    );
+    let aggregate = AggregateAnalysis::from_traces(traces, AnalysisMode::Lenient).unwrap();
+    assert_eq!(2, aggregate.messages());
+    assert_eq!(
+        vec![(1, AnalysisError::UnmatchedSpanEnd { event: 1, label: "nope".to_string() })],
+        aggregate.warnings()
+    );
+    assert!(aggregate
+        .format_table()
+        .ends_with("Warnings: 1\n  message 1: event 1: end of span nope that is not open"));
+}
+
+#[test]
+fn gas_subtotalled_by_category() {
+    let trace = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        charge("wasm_exec", 1000),
+        charge("OnBlockOpen", 2000),
+        charge("OnBlockRead", 3000),
+        charge("OnVerifySignature", 4000),
+        charge("OnNewThing", 500),
+        ret(),
+    ]);
+    let analysis = TraceAnalysis::build(trace).unwrap();
+    let call = analysis.span(1).unwrap();
+    let categories = GasCategories::new().with_override("OnNewThing", GasCategory::Execution);
+    let by_category = analysis.self_gas_by_category(call, &categories);
+    assert_eq!(
+        vec![
+            (GasCategory::Execution, GasCharge::new_millis(1500, 0)),
+            (GasCategory::IpldRead, GasCharge::new_millis(5000, 0)),
+            (GasCategory::Crypto, GasCharge::new_millis(4000, 0)),
+        ],
+        by_category.into_iter().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        "Span[1-Call(100->f06::4), self: {sum=11, ipld_read=5, crypto=4, execution=2}, \
+         total: {sum=11, ipld_read=5, crypto=4, execution=2}]",
+        analysis.format_span_by_category(call, &categories, GasFormat::Total)
+    );
+}
+
+#[test]
+fn fvm_charge_names_categorised() {
+    let categories = GasCategories::new();
+    let expected = [
+        (GasCategory::Execution, vec!["wasm_exec", "OnSyscall"]),
+        (
+            GasCategory::IpldRead,
+            vec!["OnBlockOpenBase", "OnBlockOpen", "OnBlockRead", "OnBlockStat", "OnGetRoot"],
+        ),
+        (
+            GasCategory::IpldWrite,
+            vec!["OnBlockCreate", "OnBlockLink", "OnSetRoot", "OnActorUpdate"],
+        ),
+        (
+            GasCategory::Send,
+            vec![
+                "OnChainMessage",
+                "OnChainReturnValue",
+                "OnMethodInvocation",
+                "OnValueTransfer",
+                "OnCreateActor",
+                "OnDeleteActor",
+                "OnInstallActor",
+            ],
+        ),
+        (
+            GasCategory::Context,
+            vec![
+                "OnResolveAddress",
+                "OnLookupAddress",
+                "OnActorLookup",
+                "OnGetActorCodeCid",
+                "OnGetBuiltinActorType",
+                "OnGetCodeCidForType",
+                "OnSelfBalance",
+                "OnBalanceOf",
+                "OnNetworkContext",
+                "OnMessageContext",
+                "OnGetRandomness",
+                "OnTipsetCid",
+            ],
+        ),
+        (
+            GasCategory::Crypto,
+            vec![
+                "OnRecoverSecpPublicKey",
+                "OnHashing",
+                "OnComputeUnsealedSectorCid",
+                "OnVerifySignature",
+                "OnVerifySeal",
+                "OnVerifyAggregateSeals",
+                "OnVerifyReplicaUpdate",
+                "OnVerifyPost",
+                "OnVerifyConsensusFault",
+            ],
+        ),
+        (GasCategory::Events, vec!["OnActorEvent"]),
+        (GasCategory::Memory, vec!["OnMemoryGrow"]),
+    ];
+    for (category, names) in expected {
+        for name in names {
+            assert_eq!(category, categories.category(name), "{}", name);
+        }
+    }
+    // Names from newer FVM versions are Other, however they look.
+    assert_eq!(GasCategory::Other, categories.category("OnVerifyNewProof"));
+    assert_eq!(GasCategory::Other, categories.category("OnMemoryCopy"));
+    assert_eq!(GasCategory::Other, categories.category("OnNewThing"));
+}
+
+#[test]
+fn html_report_is_self_contained() {
+    let trace = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        charge("wasm_exec", 2000),
+        span_start("load<deals>"),
+        charge("OnBlockRead", 3000),
+        call(6, 5, 7),
+        charge("wasm_exec", 4000),
+        ret(),
+        span_end("load<deals>"),
+        ret(),
+    ]);
+    let mut resolver = NameResolver::new();
+    resolver.register_actor_name(100, "alice");
+    let mut analysis = TraceAnalysis::build(trace.clone()).unwrap();
+    analysis.resolve_names(&resolver);
+    let calls = trace.invocation_tree().unwrap();
+    let mut report = HtmlReport::new("Scenario & friends");
+    report.add_message("first", &analysis, Some(&calls), &resolver, &DecoderRegistry::new());
+    report.add_message("second", &analysis, None, &resolver, &DecoderRegistry::new());
+    let html = report.render();
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Scenario &amp; friends</title>"));
+    // Contents link to each message.
+    assert!(html.contains("<a href=\"#message-1\">second</a>"));
+    // The call tree nests the inner call within the outer.
+    let outer = html.find("<summary><span class=\"label\">Call(alice-&gt;f06::4)").unwrap();
+    let inner = html.find("<summary><span class=\"label\">Call(f06-&gt;f05::7)").unwrap();
+    assert!(outer < inner);
+    // Labels are escaped in tables and the flame chart.
+    assert!(html.contains("<td style=\"padding-left: 3em\">Span(load&lt;deals&gt;)</td>"));
+    assert!(html.contains("<title>Span(load&lt;deals&gt;): 7 gas (77.8%)</title>"));
+    assert!(html.contains("<td class=\"num\" data-value=\"3000\">3</td>"));
+    // Milligas is sorted exactly, though it may exceed the integers a double holds.
+    assert!(html.contains("BigInt(cell.dataset.value)"));
+    // Nothing is loaded from elsewhere.
+    assert!(!html.contains("src="));
+    assert!(!html.contains("<link"));
+}
+
+#[test]
+fn charge_timing_reported_per_name_and_span() {
+    let timed = |name: &'static str, compute_milli, other_milli, nanos| Charge {
+        name: name.into(),
+        compute_milli,
+        other_milli,
+        elapsed: Some(Duration::from_nanos(nanos)),
+    };
+    let trace = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        timed("OnHashing", 2000, 0, 300),
+        charge("wasm_exec", 5000),
+        call(6, 5, 7),
+        timed("OnHashing", 2000, 0, 500),
+        timed("OnBlockCreate", 1000, 3000, 400),
+        ret(),
+        ret(),
+    ]);
+    let analysis = TraceAnalysis::build(trace).unwrap();
+    let outer = analysis.span(1).unwrap();
+    assert_eq!(
+        ChargeTiming::new(Duration::from_nanos(300), GasCharge::new_millis(2000, 0)),
+        outer.self_timing()
+    );
+    let total = analysis.total_timing(outer);
+    assert_eq!(3, total.count());
+    assert_eq!(Duration::from_nanos(1200), total.elapsed());
+    // Untimed charges don't dilute the rate.
+    assert_eq!(Some(150.0), total.ns_per_gas());
+    assert_eq!(Some(240.0), total.ns_per_compute_gas());
+    assert_eq!(
+        "OnBlockCreate: count=1 elapsed=400ns gas=4 compute=1 ns/gas=100.00 ns/compute=400.00
+OnHashing: count=2 elapsed=800ns gas=4 compute=4 ns/gas=200.00 ns/compute=200.00",
+        analysis.format_charge_timing()
+    );
+    // The root has no timed charges of its own, but is reported for its descendants.
+    assert_eq!(3, analysis.format_span_timing().lines().count());
+}
+
+#[test]
+fn spans_exported_as_tables() {
+    let trace = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        charge("wasm_exec", 5000),
+        span_start("load, deals"),
+        charge("ipld_get", 3000),
+        Charge { name: "ipld_put".into(), compute_milli: 1000, other_milli: 2000, elapsed: None },
+        span_end("load, deals"),
+        call(6, 5, 7),
+        charge("wasm_exec", 1000),
+        ret(),
+        ret(),
+    ]);
+    let analysis = TraceAnalysis::build(trace).unwrap();
+
+    let columns = vec![
+        SpanColumn::Span,
+        SpanColumn::Depth,
+        SpanColumn::SelfGas,
+        SpanColumn::TotalGas,
+        SpanColumn::Compute,
+        SpanColumn::Other,
+        SpanColumn::TopCharges(2),
+    ];
+    assert_eq!(
+        "Span,Depth,Self gas,Total gas,Compute gas,Other gas,Top charges
+0-Root,0,0,12,10,2,\"wasm_exec=6, ipld_get=3\"
+1-Call(100->f06::4),1,5,12,10,2,\"wasm_exec=6, ipld_get=3\"
+\"2-Span(load, deals)\",2,6,6,4,2,\"ipld_get=3, ipld_put=3\"
+3-Call(6->f05::7),2,1,1,1,0,wasm_exec=1",
+        analysis.to_csv(&TableOptions::new(columns))
+    );
+
+    // The call of less than 2 gas is collapsed into its parent. Ties keep trace order.
+    let options = TableOptions::default().sorted_by(SpanOrder::SelfGas).collapse_below(2);
+    assert_eq!(
+        "| Span | Self gas | Total gas | Top charges |
+| --- | ---: | ---: | --- |
+| 1-Call(100->f06::4) (+1 collapsed) | 6 | 12 | wasm_exec=6, ipld_get=3, ipld_put=3 |
+| 2-Span(load, deals) | 6 | 6 | ipld_get=3, ipld_put=3 |
+| 0-Root | 0 | 12 | wasm_exec=6, ipld_get=3, ipld_put=3 |",
+        analysis.to_markdown(&options)
+    );
+}
+
+#[test]
+fn hotspots_ranked_along_critical_path() {
+    let trace = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        charge("wasm_exec", 2000),
+        call(6, 5, 7),
+        charge("wasm_exec", 1000),
+        ret(),
+        call(6, 7, 8),
+        charge("wasm_exec", 1000),
+        span_start("load_deals"),
+        charge("ipld_get", 4000),
+        span_end("load_deals"),
+        ret(),
+        ret(),
+    ]);
+    let analysis = TraceAnalysis::build(trace).unwrap();
+
+    let path = analysis.critical_path().iter().map(|s| s.id()).collect::<Vec<_>>();
+    assert_eq!(
+        vec!["0-Root", "1-Call(100->f06::4)", "3-Call(6->f07::8)", "4-Span(load_deals)"],
+        path
+    );
+    let hot = analysis.hot_charges(2);
+    assert_eq!(("ipld_get", 4), (hot[0].name, hot[0].gas.total()));
+    assert_eq!(("wasm_exec", 1), (hot[1].name, hot[1].span.index()));
+    let calls = analysis.hot_calls(5).iter().map(|(s, _)| s.index()).collect::<Vec<_>>();
+    assert_eq!(vec![1, 3, 2], calls);
+    assert_eq!(
+        "Total gas: 8
+Critical path:
+  0-Root total=8 (100.0%) self=0
+  1-Call(100->f06::4) total=8 (100.0%) self=2 top=wasm_exec
+  3-Call(6->f07::8) total=5 (62.5%) self=1 top=wasm_exec
+  4-Span(load_deals) total=4 (50.0%) self=4 top=ipld_get
+Hot calls:
+  1-Call(100->f06::4) total=8 (100.0%)
+Hot charges:
+  ipld_get=4 (50.0%) in 4-Span(load_deals)
+Hot charge names:
+  ipld_get=4 (50.0%)",
+        analysis.format_hotspots(1)
+    );
+}
+
+#[test]
+fn span_gas_asserted_within_bounds() {
+    let trace = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        charge("wasm_exec", 2000),
+        span_start("verify_deals"),
+        charge("ipld_get", 4000),
+        span_end("verify_deals"),
+        call(6, 5, 7),
+        charge("wasm_exec", 1000),
+        ret(),
+        ret(),
+    ]);
+    let analysis = TraceAnalysis::build(trace).unwrap();
+
+    assert_span_gas!(analysis, "verify_deals", <= 4);
+    assert_span_gas!(analysis, SpanSelector::call(6, 4), self == 2);
+    assert_span_gas!(analysis, SpanSelector::call(5, 7), > 0);
+    assert_span_gas!(analysis, SpanSelector::Message, < 10);
+
+    let err = analysis
+        .check_span_gas(SpanSelector::call(6, 4), GasMeasure::TotalGas, GasBound::AtMost(5))
+        .unwrap_err();
+    assert_eq!(
+        "gas assertion failed: total gas of calls to f06 method 4 <= 5
+  1-Call(100->f06::4): total gas 7 is not <= 5
+    path: Root > Call(100->f06::4)
+    charges: sum=7, ipld_get=4, wasm_exec=3
+    child 2-Span(verify_deals): total gas 4
+    child 3-Call(6->f05::7): total gas 1",
+        err.to_string()
+    );
+    // A label matching no spans fails rather than passing vacuously.
+    let err = analysis
+        .check_span_gas("verify_dealz", GasMeasure::TotalGas, GasBound::AtMost(5))
+        .unwrap_err();
+    assert_eq!(
+        "gas assertion failed: no spans labelled \"verify_dealz\" to check",
+        err.to_string()
+    );
+
+    // Calls are selected by the address to which they were made.
+    let robust = Address::new_actor(b"verifier");
+    let trace =
+        ExecutionTrace::new(vec![call_to(100, robust, 4), charge("wasm_exec", 2000), ret()]);
+    let analysis = TraceAnalysis::build(trace).unwrap();
+    assert_span_gas!(analysis, SpanSelector::call_to(robust, 4), == 2);
+    assert!(analysis
+        .check_span_gas(SpanSelector::call(6, 4), GasMeasure::TotalGas, GasBound::AtMost(5))
+        .is_err());
+}
+
+#[test]
+#[should_panic(expected = "self gas 3 is not < 3")]
+fn span_gas_assertion_panics_out_of_bounds() {
+    let trace = ExecutionTrace::new(vec![
+        span_start("verify_deals"),
+        charge("ipld_get", 3000),
+        span_end("verify_deals"),
+    ]);
+    let analysis = TraceAnalysis::build(trace).unwrap();
+    assert_span_gas!(analysis, "verify_deals", self < 3);
+}
@@ -0,0 +1,77 @@
+use fvm_shared::error::ExitCode;
+use fvm_workbench_api::trace::ExecutionEvent::{GasCharge as Charge, SpanStart};
+use fvm_workbench_api::trace::ExecutionTrace;
+use serde_json::{json, Value};
+
+use crate::common::{call, charge, ret_with, span_end};
+
+mod common;
+
+#[test]
+fn calls_and_spans_exported_as_slices_on_gas_timeline() {
+    let trace = ExecutionTrace::new(vec![
+        call(100, 6, 4),
+        charge("wasm_exec", 1000),
+        SpanStart { label: "load".to_string(), attrs: vec![("n".into(), "2".into())] },
+        Charge { name: "ipld_get".into(), compute_milli: 2000, other_milli: 500, elapsed: None },
+        call(6, 5, 7),
+        charge("wasm_exec", 300),
+        ret_with(ExitCode::USR_FORBIDDEN),
+        span_end("load"),
+        span_end("nope"),
+        SpanStart { label: "verify".to_string(), attrs: vec![] },
+        call(6, 7, 8),
+        charge("wasm_exec", 100),
+    ]);
+    let exported = trace.to_chrome_trace("publish_deals");
+    let events = exported["traceEvents"].as_array().unwrap();
+    let of_phase = |ph: &str| events.iter().filter(|e| e["ph"] == ph).collect::<Vec<_>>();
+
+    // Begin and end events nest, with times in cumulative milligas.
+    // The calls and span left open by the truncated trace are closed at its end.
+    let slices = events
+        .iter()
+        .filter(|e| e["ph"] == "B" || e["ph"] == "E")
+        .map(|e| {
+            (e["ph"].as_str().unwrap(), e["name"].as_str().unwrap(), e["ts"].as_u64().unwrap())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            ("B", "Call(100->f06::4)", 0),
+            ("B", "Span(load)", 1000),
+            ("B", "Call(6->f05::7)", 3500),
+            ("E", "Call(6->f05::7)", 3800),
+            ("E", "Span(load)", 3800),
+            ("B", "Span(verify)", 3800),
+            ("B", "Call(6->f07::8)", 3800),
+            ("E", "Call(6->f07::8)", 3900),
+            ("E", "Span(verify)", 3900),
+            ("E", "Call(100->f06::4)", 3900),
+        ],
+        slices
+    );
+    let ends = of_phase("E");
+    assert_eq!(json!({ "exit_code": 18 }), ends[0]["args"]);
+    assert_eq!(json!({}), ends[1]["args"]);
+    assert!(ends[2..].iter().all(|e| e["args"] == json!({ "unclosed": true })));
+    assert_eq!(json!({ "n": "2" }), of_phase("B")[1]["args"]);
+
+    // Each charge is an instant at the gas charged before it, and the counter follows the total.
+    let charges = of_phase("i")
+        .into_iter()
+        .filter(|e| e["cat"] == "gas")
+        .map(|e| (e["name"].as_str().unwrap(), e["ts"].as_u64().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![("wasm_exec", 0), ("ipld_get", 1000), ("wasm_exec", 3500), ("wasm_exec", 3800)],
+        charges
+    );
+    let counters = of_phase("C").iter().map(|e| e["args"]["milligas"].clone()).collect::<Vec<_>>();
+    assert_eq!(vec![json!(1000), json!(3500), json!(3800), json!(3900)], counters);
+
+    // An end of a span that isn't open is an instant.
+    let unmatched: Vec<&Value> = of_phase("i").into_iter().filter(|e| e["cat"] == "span").collect();
+    assert_eq!(1, unmatched.len());
+    assert_eq!("SpanEnd(nope)", unmatched[0]["name"]);
+}
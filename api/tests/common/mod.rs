@@ -0,0 +1,80 @@
+//! Builders of execution trace events, shared by the integration tests.
+#![allow(dead_code)]
+
+use cid::multihash::Multihash;
+use cid::Cid;
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::DAG_CBOR;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::{ActorID, MethodNum};
+use fvm_workbench_api::trace::ExecutionEvent;
+
+/// A compute gas charge.
+pub fn charge(name: &'static str, compute_milli: u64) -> ExecutionEvent {
+    ExecutionEvent::GasCharge { name: name.into(), compute_milli, other_milli: 0, elapsed: None }
+}
+
+/// A call with no parameters or value.
+pub fn call(from: ActorID, to: ActorID, method: MethodNum) -> ExecutionEvent {
+    call_with(from, to, method, None, 0)
+}
+
+pub fn call_with(
+    from: ActorID,
+    to: ActorID,
+    method: MethodNum,
+    params: Option<IpldBlock>,
+    value: u64,
+) -> ExecutionEvent {
+    ExecutionEvent::Call {
+        from,
+        to: Address::new_id(to),
+        method,
+        params,
+        value: TokenAmount::from_atto(value),
+        gas_limit: 1_000_000,
+        read_only: false,
+    }
+}
+
+/// A call with no parameters or value to an address, such as an actor's robust address.
+pub fn call_to(from: ActorID, to: Address, method: MethodNum) -> ExecutionEvent {
+    ExecutionEvent::Call {
+        from,
+        to,
+        method,
+        params: None,
+        value: TokenAmount::from_atto(0),
+        gas_limit: 1_000_000,
+        read_only: false,
+    }
+}
+
+/// A successful return with no value.
+pub fn ret() -> ExecutionEvent {
+    ret_with(ExitCode::OK)
+}
+
+pub fn ret_with(exit_code: ExitCode) -> ExecutionEvent {
+    ExecutionEvent::CallReturn { return_value: None, exit_code }
+}
+
+pub fn span_start(label: &str) -> ExecutionEvent {
+    ExecutionEvent::SpanStart { label: label.to_string(), attrs: vec![] }
+}
+
+pub fn span_end(label: &str) -> ExecutionEvent {
+    ExecutionEvent::SpanEnd { label: label.to_string() }
+}
+
+/// An identity-hashed CID of some raw data, standing in for actor code.
+pub fn code(data: &[u8]) -> Cid {
+    Cid::new_v1(0x55, Multihash::wrap(0, data).unwrap())
+}
+
+/// A block of CBOR encoding a small integer.
+pub fn cbor_int(n: u8) -> IpldBlock {
+    IpldBlock { codec: DAG_CBOR, data: vec![n] }
+}
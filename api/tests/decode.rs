@@ -0,0 +1,52 @@
+use fvm_ipld_encoding::CBOR;
+use fvm_shared::event::{ActorEvent, Entry, Flags};
+use fvm_workbench_api::events::event_type;
+use fvm_workbench_api::names::NameResolver;
+use fvm_workbench_api::trace::decode::cbor_diagnostic;
+use fvm_workbench_api::trace::{ExecutionEvent, ExecutionTrace};
+
+#[test]
+fn cbor_formatted_in_diagnostic_notation() {
+    // {"a": [1, -2, h'0102'], "b": true, "c": null, "d": 1.5, "e": 42(<cid>)}
+    let mut data = vec![0xa5, 0x61, b'a', 0x83, 0x01, 0x21, 0x42, 0x01, 0x02];
+    data.extend([
+        0x61, b'b', 0xf5, 0x61, b'c', 0xf6, 0x61, b'd', 0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0,
+    ]);
+    // An identity-hashed CIDv1 of raw data "hi", preceded by the zero multibase prefix.
+    data.extend([0x61, b'e', 0xd8, 0x2a, 0x47, 0x00, 0x01, 0x55, 0x00, 0x02, b'h', b'i']);
+    assert_eq!(
+        r#"{"a": [1, -2, h'0102'], "b": true, "c": null, "d": 1.5, "e": 42(bafkqaatine)}"#,
+        cbor_diagnostic(&data).unwrap()
+    );
+
+    // Truncated and trailing data, and data that isn't DAG-CBOR, like a map with an integer key,
+    // are rejected.
+    assert!(cbor_diagnostic(&[0x82, 0x01]).is_err());
+    assert!(cbor_diagnostic(&[0x01, 0x02]).is_err());
+    assert!(cbor_diagnostic(&[0xa1, 0x01, 0x02]).is_err());
+}
+
+#[test]
+fn actor_events_formatted_in_trace() {
+    let entry = |key: &str, value: Vec<u8>| Entry {
+        flags: Flags::FLAG_INDEXED_ALL,
+        key: key.to_string(),
+        codec: CBOR,
+        value,
+    };
+    let event = ActorEvent {
+        entries: vec![
+            entry("$type", vec![0x65, b't', b'h', b'i', b'n', b'g']),
+            entry("n", vec![5]),
+        ],
+    };
+    let trace = ExecutionTrace::new(vec![ExecutionEvent::ActorEvent { emitter: 7, event }]);
+
+    let mut resolver = NameResolver::new();
+    resolver.register_actor_name(7, "market");
+    assert_eq!(r#"ActorEvent(market: $type="thing", n=5)"#, trace.format_with(&resolver));
+    let events = trace.actor_events();
+    assert_eq!(1, events.len());
+    assert_eq!(7, events[0].emitter);
+    assert_eq!(Some("thing".to_string()), event_type(&events[0].event));
+}
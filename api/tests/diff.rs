@@ -0,0 +1,95 @@
+use fvm_shared::error::ExitCode;
+use fvm_workbench_api::names::NameResolver;
+use fvm_workbench_api::trace::diff::{CallChange, DiffKind, TraceDiff};
+use fvm_workbench_api::trace::ExecutionTrace;
+use vm_api::trace::InvocationTrace;
+
+use crate::common::{call, call_with, cbor_int, charge, ret, ret_with};
+
+mod common;
+
+#[test]
+fn traces_aligned_around_added_and_removed_calls() {
+    let before = ExecutionTrace::new(vec![
+        call(100, 4, 2),
+        charge("wasm_exec", 1000),
+        call(4, 5, 3),
+        charge("wasm_exec", 2000),
+        ret(),
+        call(4, 6, 3),
+        ret(),
+        call(4, 7, 3),
+        charge("wasm_exec", 1500),
+        ret(),
+        ret(),
+    ]);
+    let after = ExecutionTrace::new(vec![
+        call(100, 4, 2),
+        charge("wasm_exec", 1000),
+        call(4, 5, 3),
+        charge("wasm_exec", 3000),
+        ret(),
+        call(4, 8, 3),
+        ret(),
+        call_with(4, 7, 3, Some(cbor_int(1)), 0),
+        charge("wasm_exec", 1500),
+        ret_with(ExitCode::USR_FORBIDDEN),
+        ret(),
+    ]);
+
+    let diff = before.diff(&after);
+    assert!(!diff.is_unchanged());
+    assert_eq!(1000, diff.gas_delta_milli());
+    let root = &diff.calls()[0];
+    assert_eq!(DiffKind::Matched, root.kind);
+    assert!(root.changes.is_empty());
+    let kinds = root.subcalls.iter().map(|c| (c.to.id().unwrap(), c.kind)).collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            (5, DiffKind::Matched),
+            (8, DiffKind::Added),
+            (6, DiffKind::Removed),
+            (7, DiffKind::Matched)
+        ],
+        kinds
+    );
+    assert_eq!(1000, root.subcalls[0].gas_delta_milli());
+    assert_eq!(2, root.subcalls[3].changes.len());
+    assert!(matches!(root.subcalls[3].changes[0], CallChange::Params { .. }));
+    assert_eq!(8, diff.first_divergence().unwrap().to.id().unwrap());
+
+    assert_eq!(
+        "  Call(f0100->f04::2) gas=5->6 (+1)
+    Call(f04->f05::3) gas=2->3 (+1)
++   Call(f04->f08::3) gas=0  <== first divergence
+-   Call(f04->f06::3) gas=0
+~   Call(f04->f07::3) gas=2->2 (+0)
+        params: none -> 1
+        outcome: exit_code=0 -> exit_code=18",
+        diff.format_with(&NameResolver::new())
+    );
+}
+
+#[test]
+fn invocation_traces_compared_without_gas() {
+    let before = InvocationTrace::try_from(ExecutionTrace::new(vec![
+        call(100, 4, 2),
+        charge("wasm_exec", 1000),
+        ret(),
+    ]))
+    .unwrap();
+    let mut after = before.clone();
+    assert!(TraceDiff::of_invocations(&before, &after).is_unchanged());
+
+    after.return_value = Some(cbor_int(2));
+    let diff = TraceDiff::of_invocations(&before, &after);
+    assert!(!diff.with_gas());
+    assert_eq!(
+        vec![CallChange::ReturnValue { before: None, after: Some(cbor_int(2)) }],
+        diff.calls()[0].changes
+    );
+    assert_eq!(
+        "~ Call(f0100->f04::2)  <== first divergence\n      return: none -> 2",
+        diff.format_with(&NameResolver::new())
+    );
+}
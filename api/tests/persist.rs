@@ -0,0 +1,124 @@
+#![cfg(feature = "persistence")]
+
+use std::time::Duration;
+
+use cid::multihash::Multihash;
+use cid::Cid;
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::{RawBytes, CBOR};
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_shared::event::{ActorEvent, Entry, Flags, StampedEvent};
+use fvm_shared::receipt::Receipt;
+use fvm_workbench_api::trace::ExecutionEvent::{
+    self, Call, CallError, CallReturn, GasCharge, InvokeActor, Ipld, Log, SpanEnd, SpanStart,
+    Unrecognized,
+};
+use fvm_workbench_api::trace::{ExecutionTrace, IpldOperation};
+use fvm_workbench_api::ExecutionResult;
+
+#[test]
+fn trace_round_trips() {
+    let trace = ExecutionTrace::new(vec![
+        GasCharge {
+            name: "OnChainMessage".into(),
+            compute_milli: 1000,
+            other_milli: 500,
+            elapsed: Some(Duration::from_nanos(1234)),
+        },
+        Call {
+            from: 100,
+            to: Address::new_id(6),
+            method: 4,
+            params: IpldBlock::serialize_cbor(&(1u64, "two")).unwrap(),
+            value: TokenAmount::from_atto(3),
+            gas_limit: 1_000_000,
+            read_only: false,
+        },
+        SpanStart { label: "load_deals".to_string(), attrs: vec![("n".into(), "1".into())] },
+        GasCharge {
+            name: format!("dynamic_{}", 1).into(),
+            compute_milli: 1000,
+            other_milli: 0,
+            elapsed: None,
+        },
+        SpanEnd { label: "load_deals".to_string() },
+        Call {
+            from: 6,
+            to: Address::new_id(5),
+            method: 7,
+            params: None,
+            value: TokenAmount::from_atto(0),
+            gas_limit: 1_000,
+            read_only: true,
+        },
+        InvokeActor { cid: code() },
+        Ipld { op: IpldOperation::Get, cid: code(), size: 42 },
+        Log { msg: "loading deals".to_string() },
+        ExecutionEvent::ActorEvent { emitter: 5, event: event() },
+        Unrecognized { description: "Future(1)".to_string() },
+        CallError { reason: "not found".to_string(), errno: ErrorNumber::NotFound },
+        CallReturn {
+            return_value: IpldBlock::serialize_cbor(&"ok").unwrap(),
+            exit_code: ExitCode::OK,
+        },
+    ]);
+
+    let json = trace.to_json().unwrap();
+    assert_eq!(trace, ExecutionTrace::from_json(&json).unwrap());
+    let cbor = trace.to_cbor().unwrap();
+    assert_eq!(trace, ExecutionTrace::from_cbor(&cbor).unwrap());
+}
+
+#[test]
+fn result_round_trips() {
+    let result = ExecutionResult {
+        receipt: Receipt {
+            exit_code: ExitCode::USR_FORBIDDEN,
+            return_data: RawBytes::new(vec![0x63, b'b', b'a', b'd']),
+            gas_used: 12345,
+            events_root: Some(code()),
+        },
+        penalty: TokenAmount::from_atto(1),
+        miner_tip: TokenAmount::from_atto(2),
+        gas_burned: 678,
+        base_fee_burn: TokenAmount::from_atto(3),
+        over_estimation_burn: TokenAmount::from_atto(4),
+        events: vec![StampedEvent::new(5, event())],
+        trace: ExecutionTrace::new(vec![
+            Call {
+                from: 100,
+                to: Address::new_id(5),
+                method: 2,
+                params: None,
+                value: TokenAmount::from_atto(0),
+                gas_limit: 1_000,
+                read_only: false,
+            },
+            ExecutionEvent::ActorEvent { emitter: 5, event: event() },
+            CallReturn { return_value: None, exit_code: ExitCode::USR_FORBIDDEN },
+        ]),
+        message: "forbidden".to_string(),
+    };
+
+    let json = result.to_json().unwrap();
+    assert_eq!(result, ExecutionResult::from_json(&json).unwrap());
+    let cbor = result.to_cbor().unwrap();
+    assert_eq!(result, ExecutionResult::from_cbor(&cbor).unwrap());
+}
+
+fn code() -> Cid {
+    Cid::new_v1(0x55, Multihash::wrap(0, b"market").unwrap())
+}
+
+fn event() -> ActorEvent {
+    ActorEvent {
+        entries: vec![Entry {
+            flags: Flags::FLAG_INDEXED_ALL,
+            key: "$type".to_string(),
+            codec: CBOR,
+            value: vec![0x64, b'd', b'e', b'a', b'l'],
+        }],
+    }
+}
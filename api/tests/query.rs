@@ -0,0 +1,139 @@
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_workbench_api::trace::query::{
+    deepest_invocation, find_invocations, first_failing_invocation, CallFilter, CallOutcome, Cmp,
+};
+use fvm_workbench_api::trace::ExecutionEvent::CallError;
+use fvm_workbench_api::trace::{ExecutionTrace, TraceConversionError};
+use vm_api::trace::InvocationTrace;
+
+use crate::common::{call, call_with, charge, ret, ret_with};
+
+mod common;
+
+#[test]
+fn calls_found_by_filter() {
+    let trace = ExecutionTrace::new(vec![
+        charge("wasm_exec", 100),
+        call_with(100, 4, 2, None, 10),
+        charge("wasm_exec", 200),
+        call(4, 1000, 1),
+        charge("wasm_exec", 300),
+        ret_with(ExitCode::USR_ILLEGAL_ARGUMENT),
+        call(4, 5, 3),
+        ret(),
+        ret_with(ExitCode::USR_ILLEGAL_ARGUMENT),
+    ]);
+
+    let calls = trace.calls();
+    assert_eq!(3, calls.len());
+    assert_eq!(500, calls[0].gas.total_milli());
+    assert_eq!(200, calls[0].self_gas.total_milli());
+    assert_eq!(300, calls[1].gas.total_milli());
+    assert_eq!(Some(0), calls[2].parent);
+    assert_eq!(
+        CallOutcome::Return { exit_code: ExitCode::USR_ILLEGAL_ARGUMENT, return_value: None },
+        calls[1].outcome
+    );
+
+    let from_power = trace.find_calls(&CallFilter::new().from(4).depth(Cmp::Ge, 1));
+    assert_eq!(vec![1, 2], from_power.iter().map(|c| c.index).collect::<Vec<_>>());
+    let parsed: CallFilter = "to=f04 method=2 exit!=0 value>0".parse().unwrap();
+    assert_eq!(vec![0], trace.find_calls(&parsed).iter().map(|c| c.index).collect::<Vec<_>>());
+    assert!("to>f04".parse::<CallFilter>().is_err());
+    assert!("size=1".parse::<CallFilter>().is_err());
+
+    // The failure originated in the call to f01000, and propagated to its caller.
+    assert_eq!(1, trace.first_failing_call().unwrap().index);
+    assert_eq!(1, trace.deepest_call().unwrap().index);
+}
+
+#[test]
+fn syscall_errors_preserved_in_invocations() {
+    let trace = ExecutionTrace::new(vec![
+        call(100, 4, 2),
+        charge("wasm_exec", 100),
+        call(4, 1000, 1),
+        charge("wasm_exec", 200),
+        CallError { reason: "actor not found".to_string(), errno: ErrorNumber::NotFound },
+        ret_with(ExitCode::USR_ILLEGAL_ARGUMENT),
+    ]);
+
+    let invocation = InvocationTrace::try_from(&trace).unwrap();
+    assert_eq!(Some(ErrorNumber::NotFound), invocation.subinvocations[0].error_number);
+
+    let tree = trace.invocation_tree().unwrap();
+    assert_eq!(Some(ExitCode::USR_ILLEGAL_ARGUMENT), tree.call.exit_code());
+    assert_eq!(300, tree.call.gas.total_milli());
+    assert_eq!(100, tree.call.self_gas.total_milli());
+    assert_eq!(
+        Some((ErrorNumber::NotFound, "actor not found")),
+        tree.subinvocations[0].call.error()
+    );
+}
+
+#[test]
+fn malformed_traces_rejected_by_conversion() {
+    let convert = |events| InvocationTrace::try_from(ExecutionTrace::new(events)).err();
+    assert_eq!(
+        Some(TraceConversionError::UnmatchedReturn { event: 2 }),
+        convert(vec![call(100, 4, 2), ret(), ret()])
+    );
+    assert_eq!(
+        Some(TraceConversionError::MultipleRoots { event: 2 }),
+        convert(vec![call(100, 4, 2), ret(), call(100, 4, 2)])
+    );
+    assert_eq!(
+        Some(TraceConversionError::UnreturnedCall { event: 0 }),
+        convert(vec![call(100, 4, 2), charge("wasm_exec", 100), call(4, 5, 2), ret()])
+    );
+    assert_eq!(Some(TraceConversionError::MissingRoot), convert(vec![charge("wasm_exec", 100)]));
+
+    // The invocation tree rejects the same problems, but leaves unreturned calls unfinished.
+    let tree = |events| ExecutionTrace::new(events).invocation_tree();
+    assert_eq!(
+        TraceConversionError::MultipleRoots { event: 2 },
+        tree(vec![call(100, 4, 2), ret(), call(100, 4, 2), ret()]).unwrap_err()
+    );
+    assert_eq!(
+        TraceConversionError::UnmatchedReturn { event: 2 },
+        tree(vec![call(100, 4, 2), ret(), ret(), call(100, 4, 2)]).unwrap_err()
+    );
+    let unfinished = tree(vec![call(100, 4, 2), call(4, 5, 2), ret()]).unwrap();
+    assert_eq!(CallOutcome::Unfinished, unfinished.call.outcome);
+    assert_eq!(1, unfinished.subinvocations[0].call.index);
+}
+
+#[test]
+fn invocations_found_by_filter_failure_and_depth() {
+    let trace = ExecutionTrace::new(vec![
+        call(100, 4, 2),
+        call(4, 5, 3),
+        call(5, 6, 1),
+        ret_with(ExitCode::USR_NOT_FOUND),
+        ret_with(ExitCode::USR_ILLEGAL_STATE),
+        call(4, 7, 3),
+        call(7, 8, 1),
+        ret(),
+        ret(),
+        ret_with(ExitCode::USR_ILLEGAL_STATE),
+    ]);
+    let invocation = InvocationTrace::try_from(&trace).unwrap();
+
+    let found = find_invocations(&invocation, &CallFilter::new().method(3));
+    assert_eq!(
+        vec![(1, 5), (1, 7)],
+        found.iter().map(|(d, i)| (*d, i.to.id().unwrap())).collect::<Vec<_>>()
+    );
+    let found = find_invocations(&invocation, &"exit!=0 depth>=1".parse().unwrap());
+    assert_eq!(vec![5, 6], found.iter().map(|(_, i)| i.to.id().unwrap()).collect::<Vec<_>>());
+
+    let (depth, failing) = first_failing_invocation(&invocation).unwrap();
+    assert_eq!((2, 6), (depth, failing.to.id().unwrap()));
+    assert_eq!(ExitCode::USR_NOT_FOUND, failing.exit_code);
+    let (depth, deepest) = deepest_invocation(&invocation);
+    assert_eq!((2, 6), (depth, deepest.to.id().unwrap()));
+
+    let ok = InvocationTrace::try_from(&ExecutionTrace::new(vec![call(100, 4, 2), ret()])).unwrap();
+    assert!(first_failing_invocation(&ok).is_none());
+    assert_eq!(0, deepest_invocation(&ok).0);
+}
@@ -0,0 +1,61 @@
+use fil_actor_init::Method as InitMethod;
+use fil_actor_market::Method as MarketMethod;
+use fil_actor_miner::Method as MinerMethod;
+use fil_actor_power::Method as PowerMethod;
+use fil_actor_verifreg::Method as VerifregMethod;
+use fvm_shared::MethodNum;
+use fvm_workbench_api::trace::decode::DecoderRegistry;
+
+/// Creates a registry decoding the parameters and return values of commonly used built-in
+/// actor methods. Type names match those of the builtin actors manifest.
+/// Register further types with the registry as needed.
+pub fn builtin_decoders() -> DecoderRegistry {
+    let mut decoders = DecoderRegistry::new();
+
+    decoders.register_params::<fil_actor_init::ExecParams>("Init", InitMethod::Exec as MethodNum);
+    decoders.register_return::<fil_actor_init::ExecReturn>("Init", InitMethod::Exec as MethodNum);
+    decoders.register_params::<fil_actor_init::Exec4Params>("Init", InitMethod::Exec4 as MethodNum);
+    decoders.register_return::<fil_actor_init::Exec4Return>("Init", InitMethod::Exec4 as MethodNum);
+
+    let create_miner = PowerMethod::CreateMiner as MethodNum;
+    decoders.register_params::<fil_actor_power::CreateMinerParams>("Power", create_miner);
+    decoders.register_return::<fil_actor_power::CreateMinerReturn>("Power", create_miner);
+
+    decoders.register_params::<fil_actor_miner::PreCommitSectorBatchParams2>(
+        "Miner",
+        MinerMethod::PreCommitSectorBatch2 as MethodNum,
+    );
+    decoders.register_params::<fil_actor_miner::ProveCommitAggregateParams>(
+        "Miner",
+        MinerMethod::ProveCommitAggregate as MethodNum,
+    );
+    decoders.register_params::<fil_actor_miner::SubmitWindowedPoStParams>(
+        "Miner",
+        MinerMethod::SubmitWindowedPoSt as MethodNum,
+    );
+    decoders.register_params::<fil_actor_miner::ExtendSectorExpiration2Params>(
+        "Miner",
+        MinerMethod::ExtendSectorExpiration2 as MethodNum,
+    );
+    decoders.register_params::<fil_actor_miner::TerminateSectorsParams>(
+        "Miner",
+        MinerMethod::TerminateSectors as MethodNum,
+    );
+    decoders.register_return::<fil_actor_miner::TerminateSectorsReturn>(
+        "Miner",
+        MinerMethod::TerminateSectors as MethodNum,
+    );
+
+    let publish_deals = MarketMethod::PublishStorageDeals as MethodNum;
+    decoders
+        .register_params::<fil_actor_market::PublishStorageDealsParams>("Market", publish_deals);
+    decoders
+        .register_return::<fil_actor_market::PublishStorageDealsReturn>("Market", publish_deals);
+
+    decoders.register_params::<fil_actor_verifreg::AddVerifiedClientParams>(
+        "VerifiedRegistry",
+        VerifregMethod::AddVerifiedClient as MethodNum,
+    );
+
+    decoders
+}
@@ -6,7 +6,9 @@ use fvm_workbench_api::{bench::WorkbenchBuilder, wrangler::ExecutionWrangler};
 use fvm_workbench_vm::{builder::FvmBenchBuilder, externs::FakeExterns};
 use genesis::{create_genesis_actors, GenesisSpec};
 
+pub mod decoders;
 pub mod genesis;
+pub mod names;
 
 /// Create an ExecutionWrangler with sensible genesis state and defaults for running imported
 /// tests from builtin-actors
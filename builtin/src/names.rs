@@ -0,0 +1,266 @@
+use fil_actors_runtime::{
+    BURNT_FUNDS_ACTOR_ID, CRON_ACTOR_ID, DATACAP_TOKEN_ACTOR_ID, EAM_ACTOR_ID, INIT_ACTOR_ID,
+    REWARD_ACTOR_ID, STORAGE_MARKET_ACTOR_ID, STORAGE_POWER_ACTOR_ID, SYSTEM_ACTOR_ID,
+    VERIFIED_REGISTRY_ACTOR_ID,
+};
+use fvm_shared::MethodNum;
+use fvm_workbench_api::names::NameResolver;
+use fvm_workbench_api::wrangler::ExecutionWrangler;
+
+// Lists (method number, name) pairs for variants of a builtin actor's Method enum.
+macro_rules! methods {
+    ($method:ty, [$($variant:ident),* $(,)?]) => {
+        [$((<$method>::$variant as MethodNum, stringify!($variant))),*]
+    };
+}
+
+/// Creates a resolver naming the built-in actor types and methods, the singleton actors,
+/// and the code of every actor currently in the wrangler's state tree.
+pub fn name_resolver(wrangler: &ExecutionWrangler) -> NameResolver {
+    let mut resolver = wrangler.name_resolver();
+    register_builtin_names(&mut resolver);
+    resolver
+}
+
+/// Registers names for the built-in singleton actors, and for the methods of each built-in
+/// actor type. Type names match those of the builtin actors manifest.
+/// The methods are listed by hand, so `tests/names.rs` checks them against the Method enums.
+pub fn register_builtin_names(resolver: &mut NameResolver) {
+    for (id, name) in [
+        (SYSTEM_ACTOR_ID, "System"),
+        (INIT_ACTOR_ID, "Init"),
+        (REWARD_ACTOR_ID, "Reward"),
+        (CRON_ACTOR_ID, "Cron"),
+        (STORAGE_POWER_ACTOR_ID, "Power"),
+        (STORAGE_MARKET_ACTOR_ID, "Market"),
+        (VERIFIED_REGISTRY_ACTOR_ID, "VerifiedRegistry"),
+        (DATACAP_TOKEN_ACTOR_ID, "DataCap"),
+        (EAM_ACTOR_ID, "EAM"),
+        (BURNT_FUNDS_ACTOR_ID, "BurntFunds"),
+    ] {
+        resolver.register_actor_name(id, name);
+    }
+
+    let numbered: &[(&str, &[(MethodNum, &str)])] = &[
+        ("System", &methods!(fil_actor_system::Method, [Constructor])),
+        ("Init", &methods!(fil_actor_init::Method, [Constructor, Exec, Exec4])),
+        ("Cron", &methods!(fil_actor_cron::Method, [Constructor, EpochTick])),
+        ("Account", &methods!(fil_actor_account::Method, [Constructor, PubkeyAddress])),
+        (
+            "Reward",
+            &methods!(
+                fil_actor_reward::Method,
+                [Constructor, AwardBlockReward, ThisEpochReward, UpdateNetworkKPI]
+            ),
+        ),
+        (
+            "Power",
+            &methods!(
+                fil_actor_power::Method,
+                [
+                    Constructor,
+                    CreateMiner,
+                    UpdateClaimedPower,
+                    EnrollCronEvent,
+                    OnEpochTickEnd,
+                    UpdatePledgeTotal,
+                    SubmitPoRepForBulkVerify,
+                    CurrentTotalPower,
+                ]
+            ),
+        ),
+        (
+            "Miner",
+            &methods!(
+                fil_actor_miner::Method,
+                [
+                    Constructor,
+                    ControlAddresses,
+                    ChangeWorkerAddress,
+                    ChangePeerID,
+                    SubmitWindowedPoSt,
+                    ProveCommitSector,
+                    ExtendSectorExpiration,
+                    TerminateSectors,
+                    DeclareFaults,
+                    DeclareFaultsRecovered,
+                    OnDeferredCronEvent,
+                    CheckSectorProven,
+                    ApplyRewards,
+                    ReportConsensusFault,
+                    WithdrawBalance,
+                    ConfirmSectorProofsValid,
+                    ChangeMultiaddrs,
+                    CompactPartitions,
+                    CompactSectorNumbers,
+                    ConfirmChangeWorkerAddress,
+                    RepayDebt,
+                    ChangeOwnerAddress,
+                    DisputeWindowedPoSt,
+                    ProveCommitAggregate,
+                    ProveReplicaUpdates,
+                    PreCommitSectorBatch2,
+                    ProveReplicaUpdates2,
+                    ChangeBeneficiary,
+                    GetBeneficiary,
+                    ExtendSectorExpiration2,
+                    MovePartitions,
+                ]
+            ),
+        ),
+        (
+            "Market",
+            &methods!(
+                fil_actor_market::Method,
+                [
+                    Constructor,
+                    AddBalance,
+                    WithdrawBalance,
+                    PublishStorageDeals,
+                    VerifyDealsForActivation,
+                    ActivateDeals,
+                    OnMinerSectorsTerminate,
+                    CronTick,
+                ]
+            ),
+        ),
+        (
+            "VerifiedRegistry",
+            &methods!(
+                fil_actor_verifreg::Method,
+                [
+                    Constructor,
+                    AddVerifier,
+                    RemoveVerifier,
+                    AddVerifiedClient,
+                    RemoveVerifiedClientDataCap,
+                    RemoveExpiredAllocations,
+                    ClaimAllocations,
+                    GetClaims,
+                    ExtendClaimTerms,
+                    RemoveExpiredClaims,
+                ]
+            ),
+        ),
+        (
+            "Multisig",
+            &methods!(
+                fil_actor_multisig::Method,
+                [
+                    Constructor,
+                    Propose,
+                    Approve,
+                    Cancel,
+                    AddSigner,
+                    RemoveSigner,
+                    SwapSigner,
+                    ChangeNumApprovalsThreshold,
+                    LockBalance,
+                ]
+            ),
+        ),
+        (
+            "PaymentChannel",
+            &methods!(fil_actor_paych::Method, [Constructor, UpdateChannelState, Settle, Collect]),
+        ),
+        ("DataCap", &methods!(fil_actor_datacap::Method, [Constructor])),
+    ];
+    for (type_name, methods) in numbered {
+        for (method, name) in methods.iter() {
+            resolver.register_method(type_name, *method, name);
+        }
+    }
+
+    // Methods exported with FRC-0042 hashed numbers, named as hashed.
+    let exported: &[(&str, &[&str])] = &[
+        ("Account", &["AuthenticateMessage", "Receive"]),
+        ("Init", &["Exec"]),
+        (
+            "Power",
+            &[
+                "CreateMiner",
+                "NetworkRawPower",
+                "MinerRawPower",
+                "MinerCount",
+                "MinerConsensusCount",
+            ],
+        ),
+        (
+            "Miner",
+            &[
+                "ChangeWorkerAddress",
+                "ChangePeerID",
+                "WithdrawBalance",
+                "ChangeMultiaddrs",
+                "ConfirmChangeWorkerAddress",
+                "RepayDebt",
+                "ChangeOwnerAddress",
+                "ChangeBeneficiary",
+                "GetBeneficiary",
+                "GetOwner",
+                "IsControllingAddress",
+                "GetSectorSize",
+                "GetAvailableBalance",
+                "GetVestingFunds",
+                "GetPeerID",
+                "GetMultiaddrs",
+            ],
+        ),
+        (
+            "Market",
+            &[
+                "AddBalance",
+                "WithdrawBalance",
+                "PublishStorageDeals",
+                "GetBalance",
+                "GetDealDataCommitment",
+                "GetDealClient",
+                "GetDealProvider",
+                "GetDealLabel",
+                "GetDealTerm",
+                "GetDealTotalPrice",
+                "GetDealClientCollateral",
+                "GetDealProviderCollateral",
+                "GetDealVerified",
+                "GetDealActivation",
+            ],
+        ),
+        (
+            "VerifiedRegistry",
+            &[
+                "AddVerifiedClient",
+                "RemoveExpiredAllocations",
+                "GetClaims",
+                "ExtendClaimTerms",
+                "RemoveExpiredClaims",
+                "Receive",
+            ],
+        ),
+        (
+            "DataCap",
+            &[
+                "Mint",
+                "Destroy",
+                "Name",
+                "Symbol",
+                "TotalSupply",
+                "Balance",
+                "Transfer",
+                "TransferFrom",
+                "IncreaseAllowance",
+                "DecreaseAllowance",
+                "RevokeAllowance",
+                "Burn",
+                "BurnFrom",
+                "Allowance",
+                "Granularity",
+            ],
+        ),
+        ("Multisig", &["Receive"]),
+    ];
+    for (type_name, names) in exported {
+        for name in names.iter() {
+            resolver.register_exported_method(type_name, name);
+        }
+    }
+}
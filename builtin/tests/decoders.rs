@@ -0,0 +1,34 @@
+use fil_actor_init::Method as InitMethod;
+use fil_actor_power::{CreateMinerParams, Method as PowerMethod};
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_shared::address::Address;
+use fvm_shared::sector::RegisteredPoStProof;
+use fvm_shared::MethodNum;
+use fvm_workbench_builtin_actors::decoders::builtin_decoders;
+
+#[test]
+fn builtin_params_decoded_or_shown_as_cbor() {
+    let decoders = builtin_decoders();
+    let params = CreateMinerParams {
+        owner: Address::new_id(100),
+        worker: Address::new_id(101),
+        window_post_proof_type: RegisteredPoStProof::StackedDRGWindow2KiBV1P1,
+        peer: vec![1, 2],
+        multiaddrs: vec![],
+    };
+    let block = IpldBlock::serialize_cbor(&params).unwrap().unwrap();
+    let create_miner = PowerMethod::CreateMiner as MethodNum;
+    assert_eq!(
+        format!("{:?}", params),
+        decoders.format_params(Some("Power"), create_miner, &block)
+    );
+
+    // Without a decoder for the actor type and method, the parameters are shown as CBOR.
+    let cbor = "[h'0064', h'0065', 10, h'0102', []]";
+    assert_eq!(cbor, decoders.format_params(Some("Power"), 999, &block));
+    assert_eq!(cbor, decoders.format_params(None, create_miner, &block));
+    assert_eq!(cbor, decoders.format_return(Some("Market"), create_miner, &block));
+    // As they are if they don't decode as the registered type.
+    let exec = InitMethod::Exec as MethodNum;
+    assert_eq!(cbor, decoders.format_params(Some("Init"), exec, &block));
+}
@@ -1,7 +1,7 @@
 use fil_actors_integration_tests::util::assert_invariants;
 use fil_actors_runtime::runtime::Policy;
 use fil_actors_runtime::test_utils::FakePrimitives;
-use fil_actors_runtime::INIT_ACTOR_ADDR;
+use fil_actors_runtime::{INIT_ACTOR_ADDR, INIT_ACTOR_ID};
 use fvm_actor_utils::shared_blockstore::SharedMemoryBlockstore;
 use fvm_shared::bigint::Zero;
 use fvm_shared::econ::TokenAmount;
@@ -9,12 +9,21 @@ use fvm_shared::error::ExitCode;
 use fvm_shared::state::StateTreeVersion;
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::METHOD_SEND;
-use fvm_workbench_api::analysis::TraceAnalysis;
+use fvm_workbench_api::analysis::aggregate::AggregateAnalysis;
+use fvm_workbench_api::analysis::assertion::SpanSelector;
+use fvm_workbench_api::analysis::attribution::ActorGasAttribution;
+use fvm_workbench_api::analysis::baseline::{CompareOptions, GasBaseline, Thresholds};
+use fvm_workbench_api::analysis::category::GasCategories;
+use fvm_workbench_api::analysis::{AnalysisMode, GasFormat, TraceAnalysis};
+use fvm_workbench_api::assert_span_gas;
 use fvm_workbench_api::bench::WorkbenchBuilder;
+use fvm_workbench_api::trace::ExecutionEvent;
 use fvm_workbench_api::wrangler::ExecutionWrangler;
+use fvm_workbench_builtin_actors::decoders::builtin_decoders;
 use fvm_workbench_builtin_actors::genesis::{
     create_genesis_actors, GenesisSpec, BUILTIN_ACTORS_BUNDLE,
 };
+use fvm_workbench_builtin_actors::names::name_resolver;
 use fvm_workbench_vm::builder::FvmBenchBuilder;
 use fvm_workbench_vm::externs::FakeExterns;
 use vm_api::VM;
@@ -45,9 +54,55 @@ fn test_hookup() {
     assert_eq!(ExitCode::OK, result.code);
 
     let trace = wrangler.peek_execution_trace().unwrap();
-    println!("{}", trace.format());
-    let analysis = TraceAnalysis::build(trace.clone());
-    println!("{}", analysis.format_spans());
+    let mut resolver = name_resolver(&wrangler);
+    resolver.register_actor_name(genesis.faucet_id, "faucet");
+    println!("{}", trace.format_decoded(&resolver, &builtin_decoders()));
+    assert!(trace.first_failing_call().is_none());
+    let mut analysis = TraceAnalysis::build(trace.clone()).unwrap();
+    analysis.resolve_names(&resolver);
+    println!("{}", analysis.format_spans_with(GasFormat::Dimensions));
+    println!("{}", analysis.format_spans_by_category(&GasCategories::new(), GasFormat::Total));
+    println!("{}", analysis.format_folded_stacks());
+    println!("{}", ActorGasAttribution::from_trace(&trace).format_report(&resolver));
+    // The message's send to the Init actor is the only call from the root.
+    let root = analysis.root();
+    assert_eq!(
+        vec!["Call(faucet->Init::Send)"],
+        analysis.children(root).map(|s| s.label()).collect::<Vec<_>>()
+    );
+    let calls = trace.invocation_tree().unwrap();
+    let html = analysis.to_html("hookup", Some(&calls), &resolver, &builtin_decoders());
+    assert!(html.contains("Call(faucet-&gt;Init::Send)"));
+    let charged: u64 = trace
+        .events()
+        .iter()
+        .map(|e| match e {
+            ExecutionEvent::GasCharge { compute_milli, other_milli, .. } => {
+                compute_milli + other_milli
+            }
+            _ => 0,
+        })
+        .sum();
+    assert_eq!(charged, analysis.total_gas(root).total_milli());
+    // The send costs no more than the whole message, which is within a generous budget.
+    let total = analysis.total_gas(root).total();
+    assert_span_gas!(analysis, SpanSelector::call(INIT_ACTOR_ID, METHOD_SEND), <= total);
+    assert_span_gas!(wrangler, SpanSelector::Message, <= 10_000_000);
+    // An analysis is unchanged from its own baseline.
+    let baseline = GasBaseline::from_analysis(&analysis);
+    let comparison = baseline.compare(&analysis, &CompareOptions::default());
+    assert!(comparison.is_unchanged(), "{}", comparison.format_report());
+    // The message costs no more than 5% above its saved baseline.
+    let options = CompareOptions::new(Thresholds { absolute_milli: 0, relative: 0.05 });
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/baselines/hookup.json");
+    let comparison = GasBaseline::check_saved(&analysis, path, &options).unwrap();
+    println!("{}", comparison.format_report());
+    assert!(!comparison.has_regressions(), "{}", comparison.format_report());
+
+    let aggregate =
+        AggregateAnalysis::from_traces(&wrangler.execution_traces(), AnalysisMode::Strict).unwrap();
+    println!("{}", aggregate.format_table());
+    assert_eq!(1, aggregate.messages());
 
     // check that the genesis state obeys state-invariants
     assert_invariants(&wrangler, &Policy::default(), Some(genesis.total_supply));
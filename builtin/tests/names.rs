@@ -0,0 +1,39 @@
+use fvm_shared::MethodNum;
+use fvm_workbench_api::names::NameResolver;
+use fvm_workbench_builtin_actors::names::register_builtin_names;
+use num_traits::FromPrimitive;
+
+// Returns the numbers of an actor's Method enum below the FRC-0042 exported range which the
+// resolver doesn't name. All builtin actors number their methods well below 1024.
+macro_rules! unnamed_methods {
+    ($resolver:expr, $type_name:expr, $method:ty) => {
+        (1..1024 as MethodNum)
+            .filter(|n| <$method as FromPrimitive>::from_u64(*n).is_some())
+            .filter(|n| $resolver.method_name(Some($type_name), *n) == n.to_string())
+            .map(|n| format!("{}::{}", $type_name, n))
+            .collect::<Vec<_>>()
+    };
+}
+
+#[test]
+fn every_numbered_builtin_method_named() {
+    let mut resolver = NameResolver::new();
+    register_builtin_names(&mut resolver);
+
+    let unnamed = [
+        unnamed_methods!(resolver, "System", fil_actor_system::Method),
+        unnamed_methods!(resolver, "Init", fil_actor_init::Method),
+        unnamed_methods!(resolver, "Cron", fil_actor_cron::Method),
+        unnamed_methods!(resolver, "Account", fil_actor_account::Method),
+        unnamed_methods!(resolver, "Reward", fil_actor_reward::Method),
+        unnamed_methods!(resolver, "Power", fil_actor_power::Method),
+        unnamed_methods!(resolver, "Miner", fil_actor_miner::Method),
+        unnamed_methods!(resolver, "Market", fil_actor_market::Method),
+        unnamed_methods!(resolver, "VerifiedRegistry", fil_actor_verifreg::Method),
+        unnamed_methods!(resolver, "Multisig", fil_actor_multisig::Method),
+        unnamed_methods!(resolver, "PaymentChannel", fil_actor_paych::Method),
+        unnamed_methods!(resolver, "DataCap", fil_actor_datacap::Method),
+    ]
+    .concat();
+    assert!(unnamed.is_empty(), "methods missing from the name tables: {:?}", unnamed);
+}
@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+use std::num::NonZeroU64;
+
+use cid::Cid;
+use fil_actor_multisig::ProposeParams;
+use fil_actor_verifreg::AddVerifierParams;
+use fil_actors_integration_tests::util::create_accounts;
+use fil_actors_integration_tests::{
+    TEST_FAUCET_ADDR, TEST_VERIFREG_ROOT_ADDR, TEST_VERIFREG_ROOT_SIGNER_ADDR,
+};
+use fil_actors_runtime::test_utils::FakePrimitives;
+use fil_actors_runtime::{VERIFIED_REGISTRY_ACTOR_ADDR, VERIFIED_REGISTRY_ACTOR_ID};
+use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::{to_vec, RawBytes, CBOR};
+use fvm_shared::address::Address;
+use fvm_shared::bigint::Zero;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::event::{ActorEvent, Entry, Flags, StampedEvent};
+use fvm_shared::message::Message;
+use fvm_shared::receipt::Receipt;
+use fvm_shared::sector::StoragePower;
+use fvm_shared::{ActorID, MethodNum, METHOD_SEND};
+use fvm_workbench_api::analysis::assertion::SpanSelector;
+use fvm_workbench_api::assert_span_gas;
+use fvm_workbench_api::bench::Bench;
+use fvm_workbench_api::trace::{ExecutionEvent, ExecutionTrace};
+use fvm_workbench_api::wrangler::{ExecutionWrangler, TraceRetention};
+use fvm_workbench_api::ExecutionResult;
+use fvm_workbench_builtin_actors::setup;
+use vm_api::{ActorState, VM};
+
+// A method no built-in actor handles, so calling it fails.
+const UNHANDLED_METHOD: MethodNum = 1000;
+
+// Executes a message from the faucet to a built-in actor.
+fn send(w: &ExecutionWrangler, to: ActorID, method: MethodNum) {
+    w.execute_message(&TEST_FAUCET_ADDR, &Address::new_id(to), &TokenAmount::zero(), method, None)
+        .unwrap();
+}
+
+// The receiver of each message whose trace was kept, and whether it was kept in full.
+fn kept(w: &ExecutionWrangler) -> Vec<(ActorID, bool)> {
+    w.execution_traces()
+        .iter()
+        .map(|trace| {
+            let to = trace.calls()[0].to.id().unwrap();
+            let full = trace.events().iter().any(|e| matches!(e, ExecutionEvent::GasCharge { .. }));
+            (to, full)
+        })
+        .collect()
+}
+
+#[test]
+fn traces_retained_by_policy() {
+    let w = setup().with_trace_retention(TraceRetention::all().last(2));
+    for to in 1..=3 {
+        send(&w, to, METHOD_SEND);
+    }
+    assert_eq!(vec![(2, true), (3, true)], kept(&w));
+
+    let w = setup().with_trace_retention(TraceRetention::all().failures_only());
+    send(&w, 1, METHOD_SEND);
+    send(&w, 2, UNHANDLED_METHOD);
+    send(&w, 3, METHOD_SEND);
+    send(&w, 4, UNHANDLED_METHOD);
+    assert_eq!(vec![(2, true), (4, true)], kept(&w));
+
+    let w = setup().with_trace_retention(
+        TraceRetention::all().sample(NonZeroU64::new(2).unwrap()).structure_only(),
+    );
+    for to in 1..=4 {
+        send(&w, to, METHOD_SEND);
+    }
+    assert_eq!(vec![(1, false), (3, false)], kept(&w));
+}
+
+#[test]
+fn next_trace_kept_in_full() {
+    let w = setup().with_trace_retention(
+        TraceRetention::all().sample(NonZeroU64::new(2).unwrap()).structure_only(),
+    );
+    send(&w, 1, METHOD_SEND);
+    w.trace_next_message_in_full();
+    send(&w, 2, METHOD_SEND);
+    // The override applies to one message only, and isn't counted when sampling.
+    send(&w, 3, METHOD_SEND);
+    send(&w, 4, METHOD_SEND);
+    assert_eq!(vec![(1, false), (2, true), (4, false)], kept(&w));
+}
+
+#[test]
+fn peeked_trace_is_last_message_only() {
+    let w = setup().with_trace_retention(TraceRetention::all().failures_only());
+    send(&w, 2, UNHANDLED_METHOD);
+    assert_eq!(2, w.peek_execution_trace().unwrap().calls()[0].to.id().unwrap());
+    // The trace of the earlier message is still kept, but isn't the last message's.
+    send(&w, 1, METHOD_SEND);
+    assert!(w.peek_execution_trace().is_none());
+    assert_eq!(vec![(2, true)], kept(&w));
+}
+
+#[test]
+#[should_panic(expected = "trace of the last message was not kept in full")]
+fn span_gas_not_asserted_on_structure_only() {
+    let w = setup().with_trace_retention(TraceRetention::all().structure_only());
+    send(&w, 1, METHOD_SEND);
+    assert_span_gas!(w, SpanSelector::Message, <= 10_000_000);
+}
+
+#[test]
+#[should_panic(expected = "trace of the last message was not kept in full")]
+fn span_gas_not_asserted_on_earlier_message() {
+    let w = setup().with_trace_retention(TraceRetention::all().failures_only());
+    send(&w, 2, UNHANDLED_METHOD);
+    send(&w, 1, METHOD_SEND);
+    assert_span_gas!(w, SpanSelector::Message, <= 10_000_000);
+}
+
+#[test]
+#[should_panic(expected = "trace of the last message has no gas charges")]
+fn span_gas_not_asserted_without_charges() {
+    let w = event_wrangler();
+    send(&w, 1000, METHOD_SEND);
+    assert_span_gas!(w, SpanSelector::Message, <= 10_000_000);
+}
+
+#[test]
+#[cfg(feature = "calibration")]
+fn charges_timed_when_calibrating() {
+    use fvm_workbench_api::analysis::TraceAnalysis;
+
+    let w = setup();
+    send(&w, 1, METHOD_SEND);
+    let trace = w.peek_execution_trace().unwrap();
+    assert!(trace
+        .events()
+        .iter()
+        .any(|e| matches!(e, ExecutionEvent::GasCharge { elapsed: Some(_), .. })));
+    let analysis = TraceAnalysis::build(trace).unwrap();
+    assert!(analysis.total_timing(analysis.root()).count() > 0);
+}
+
+#[test]
+fn traces_kept_when_invocations_malformed() {
+    // The event bench's traces have no calls, so can't be converted to invocations.
+    let w = event_wrangler();
+    send(&w, 1000, METHOD_SEND);
+    assert!(w.try_take_invocations().is_err());
+    assert_eq!(1, w.execution_traces().len());
+}
+
+#[test]
+fn last_events_inspected() {
+    let w = event_wrangler();
+    send(&w, 1000, EMITTING_METHOD);
+    let events = emitted_events();
+    assert_eq!(events, w.last_events());
+    assert_eq!(vec![events[1].clone()], w.last_events_of_type("deal-activated"));
+    assert!(w.last_events_of_type("deal-terminated").is_empty());
+    w.assert_last_events(&events);
+
+    // Events are those of the last message only.
+    send(&w, 1000, METHOD_SEND);
+    assert!(w.last_events().is_empty());
+    w.assert_last_events(&[]);
+}
+
+#[test]
+fn builtin_actor_events_traced() {
+    let w = setup();
+    let verifier = create_accounts(&w, 1, &TokenAmount::from_whole(10_000))[0];
+    // Add a verifier through the root multisig, as the integration tests' helper does,
+    // but executing the message directly so its trace is kept for inspection.
+    let add_verifier =
+        AddVerifierParams { address: verifier, allowance: StoragePower::from(1_u64 << 32) };
+    let propose = ProposeParams {
+        to: VERIFIED_REGISTRY_ACTOR_ADDR,
+        value: TokenAmount::zero(),
+        method: fil_actor_verifreg::Method::AddVerifier as MethodNum,
+        params: RawBytes::serialize(&add_verifier).unwrap(),
+    };
+    let result = w
+        .execute_message(
+            &TEST_VERIFREG_ROOT_SIGNER_ADDR,
+            &TEST_VERIFREG_ROOT_ADDR,
+            &TokenAmount::zero(),
+            fil_actor_multisig::Method::Propose as MethodNum,
+            IpldBlock::serialize_cbor(&propose).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(ExitCode::OK, result.code);
+
+    let balances = w.last_events_of_type("verifier-balance");
+    assert_eq!(1, balances.len(), "{:?}", w.last_events());
+    assert_eq!(VERIFIED_REGISTRY_ACTOR_ID, balances[0].emitter);
+
+    // The event appears in the trace within the verified registry's call,
+    // in place of the marker logged when it was emitted.
+    let trace = w.peek_execution_trace().unwrap();
+    let add = trace
+        .calls()
+        .into_iter()
+        .find(|c| c.to == VERIFIED_REGISTRY_ACTOR_ADDR)
+        .expect("no call to the verified registry");
+    let events = trace.events();
+    let emitted = events
+        .iter()
+        .position(|e| {
+            matches!(e, ExecutionEvent::ActorEvent { emitter, event }
+                if *emitter == VERIFIED_REGISTRY_ACTOR_ID && *event == balances[0].event)
+        })
+        .expect("event not in trace");
+    assert!(add.call_event < emitted && Some(emitted) < add.return_event);
+    assert!(!events
+        .iter()
+        .any(|e| matches!(e, ExecutionEvent::Log { msg } if msg.contains("workbench:event"))));
+}
+
+#[test]
+#[should_panic(expected = "unexpected actor events")]
+fn unexpected_events_rejected() {
+    let w = event_wrangler();
+    send(&w, 1000, EMITTING_METHOD);
+    w.assert_last_events(&emitted_events()[..1]);
+}
+
+// The method for which the event bench emits events.
+const EMITTING_METHOD: MethodNum = 2;
+
+// A wrangler over a bench that emits events, rather than executing messages.
+fn event_wrangler() -> ExecutionWrangler {
+    ExecutionWrangler::new_default(
+        Box::new(EventBench(MemoryBlockstore::new())),
+        Box::new(MemoryBlockstore::new()),
+        Box::<FakePrimitives>::default(),
+    )
+}
+
+fn emitted_events() -> Vec<StampedEvent> {
+    let event = |event_type: &str| ActorEvent {
+        entries: vec![Entry {
+            flags: Flags::FLAG_INDEXED_ALL,
+            key: "$type".to_string(),
+            codec: CBOR,
+            value: to_vec(event_type).unwrap(),
+        }],
+    };
+    vec![
+        StampedEvent::new(1000, event("deal-published")),
+        StampedEvent::new(1000, event("deal-activated")),
+    ]
+}
+
+// A bench on which every message succeeds, emitting events if it calls the emitting method.
+struct EventBench(MemoryBlockstore);
+
+impl Bench for EventBench {
+    fn execute(&mut self, msg: Message, _msg_length: usize) -> anyhow::Result<ExecutionResult> {
+        let events = if msg.method_num == EMITTING_METHOD { emitted_events() } else { Vec::new() };
+        Ok(ExecutionResult {
+            receipt: Receipt {
+                exit_code: ExitCode::OK,
+                return_data: Default::default(),
+                gas_used: 0,
+                events_root: None,
+            },
+            penalty: TokenAmount::zero(),
+            miner_tip: TokenAmount::zero(),
+            gas_burned: 0,
+            base_fee_burn: TokenAmount::zero(),
+            over_estimation_burn: TokenAmount::zero(),
+            events,
+            trace: ExecutionTrace::new(Vec::new()),
+            message: String::new(),
+        })
+    }
+
+    fn execute_implicit(
+        &mut self,
+        msg: Message,
+        msg_length: usize,
+    ) -> anyhow::Result<ExecutionResult> {
+        self.execute(msg, msg_length)
+    }
+
+    fn store(&self) -> &dyn Blockstore {
+        &self.0
+    }
+
+    fn find_actor(&self, _id: ActorID) -> anyhow::Result<Option<ActorState>> {
+        Ok(None)
+    }
+
+    fn set_actor(&mut self, _key: &Address, _state: ActorState) {
+        unimplemented!()
+    }
+
+    fn resolve_address(&self, addr: &Address) -> anyhow::Result<Option<ActorID>> {
+        Ok(addr.id().ok())
+    }
+
+    fn flush(&mut self) -> Cid {
+        unimplemented!()
+    }
+
+    fn builtin_actors_manifest(&self) -> BTreeMap<Cid, vm_api::builtin::Type> {
+        BTreeMap::new()
+    }
+
+    fn actor_states(&self) -> BTreeMap<Address, ActorState> {
+        BTreeMap::new()
+    }
+
+    fn epoch(&self) -> ChainEpoch {
+        0
+    }
+
+    fn set_epoch(&mut self, _epoch: ChainEpoch) {
+        unimplemented!()
+    }
+
+    fn circulating_supply(&self) -> TokenAmount {
+        TokenAmount::zero()
+    }
+
+    fn set_circulating_supply(&mut self, _amount: TokenAmount) {
+        unimplemented!()
+    }
+
+    fn base_fee(&self) -> TokenAmount {
+        TokenAmount::zero()
+    }
+
+    fn set_base_fee(&mut self, _amount: TokenAmount) {
+        unimplemented!()
+    }
+
+    fn timestamp(&self) -> u64 {
+        0
+    }
+
+    fn set_timestamp(&mut self, _timestamp: u64) {
+        unimplemented!()
+    }
+
+    fn initial_state_root(&self) -> Cid {
+        unimplemented!()
+    }
+
+    fn set_initial_state_root(&mut self, _state_root: Cid) {
+        unimplemented!()
+    }
+
+    fn set_tracing(&mut self, _tracing: bool) {}
+}
@@ -0,0 +1,70 @@
+//! Actor events recorded in the trace at the point they are emitted.
+//!
+//! The FVM collects emitted events separately from its execution trace, and discards those of
+//! calls that abort. So the kernel records each event it emits here, and marks the point at which
+//! it was emitted with a tagged log message in the FVM trace. Conversion of the FVM trace then
+//! replaces each marker with the event recorded for it. The marker shares the reserved prefix of
+//! span tags, so actor log messages are escaped and can't be mistaken for it.
+
+use std::cell::RefCell;
+
+use fvm_shared::event::{ActorEvent, Entry, StampedEvent};
+use fvm_shared::sys::EventEntry;
+use fvm_shared::ActorID;
+use fvm_workbench_api::trace::ExecutionEvent;
+
+/// Log message marking the point at which an event was emitted.
+pub const EVENT_MARKER: &str = "\u{0}workbench:event";
+
+thread_local! {
+    // Events emitted by the execution in progress, in order.
+    // The FVM constructs a kernel for each call, so a kernel can't be handed a place to record
+    // them, but execution runs on the thread that applies the message.
+    static EMITTED: RefCell<Vec<StampedEvent>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Records an event emitted by an actor, returning the log message that marks it in the trace.
+/// Returns None if the event's entries are inconsistent with its keys and values, though
+/// such an event would have been rejected by the kernel.
+pub fn record_event(
+    emitter: ActorID,
+    headers: &[EventEntry],
+    raw_key: &[u8],
+    raw_val: &[u8],
+) -> Option<&'static str> {
+    let mut entries = Vec::with_capacity(headers.len());
+    let (mut key_pos, mut val_pos) = (0, 0);
+    for header in headers {
+        let key = raw_key.get(key_pos..key_pos + header.key_len as usize)?;
+        let value = raw_val.get(val_pos..val_pos + header.val_len as usize)?;
+        key_pos += key.len();
+        val_pos += value.len();
+        entries.push(Entry {
+            flags: header.flags,
+            key: String::from_utf8(key.to_vec()).ok()?,
+            codec: header.codec,
+            value: value.to_vec(),
+        });
+    }
+    let event = StampedEvent::new(emitter, ActorEvent { entries });
+    EMITTED.with(|emitted| emitted.borrow_mut().push(event));
+    Some(EVENT_MARKER)
+}
+
+/// Takes the events recorded since they were last taken, in the order they were emitted.
+pub fn take_events() -> Vec<StampedEvent> {
+    EMITTED.with(|emitted| emitted.take())
+}
+
+/// Converts a log message recorded in the FVM trace to an actor event, if it marks one,
+/// taking the next of the events recorded during the execution.
+pub fn untag_event(
+    msg: &str,
+    recorded: &mut impl Iterator<Item = StampedEvent>,
+) -> Option<ExecutionEvent> {
+    if msg != EVENT_MARKER {
+        return None;
+    }
+    let stamped = recorded.next()?;
+    Some(ExecutionEvent::ActorEvent { emitter: stamped.emitter, event: stamped.event })
+}
@@ -29,6 +29,8 @@ use fvm_shared::{ActorID, MethodNum};
 use multihash::derive::Multihash;
 use multihash::{MultihashDigest, MultihashGeneric};
 
+use crate::bench::{event, span};
+
 pub const TEST_VM_RAND_ARRAY: [u8; 32] = [
     1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
     26, 27, 28, 29, 30, 31, 32,
@@ -315,13 +317,14 @@ where
     }
 }
 
-/// All DebugOps are forwarded to the DefaultKernel
+/// DebugOps are forwarded to the DefaultKernel, but log messages delimiting spans are tagged
+/// so they can be converted to span events in the trace.
 impl<C> DebugOps for BenchKernel<C>
 where
     C: CallManager,
 {
     fn log(&self, msg: String) {
-        self.inner_kernel.log(msg)
+        self.inner_kernel.log(span::tag_log(msg))
     }
 
     fn debug_enabled(&self) -> bool {
@@ -423,7 +426,8 @@ where
     }
 }
 
-/// All EventOps are forwarded to the DefaultKernel
+/// EventOps are forwarded to the DefaultKernel, and each event emitted is also recorded, with a
+/// marker in the trace so it can be converted to an actor event at the point it was emitted.
 impl<C> EventOps for BenchKernel<C>
 where
     C: CallManager,
@@ -434,7 +438,12 @@ where
         raw_key: &[u8],
         raw_val: &[u8],
     ) -> fvm::kernel::Result<()> {
-        self.inner_kernel.emit_event(event_headers, raw_key, raw_val)
+        let emitter = self.inner_kernel.msg_context()?.receiver;
+        self.inner_kernel.emit_event(event_headers, raw_key, raw_val)?;
+        if let Some(marker) = event::record_event(emitter, event_headers, raw_key, raw_val) {
+            self.inner_kernel.log(marker.to_string());
+        }
+        Ok(())
     }
 }
 
@@ -7,17 +7,18 @@ use fvm::call_manager::DefaultCallManager;
 use fvm::engine::EnginePool;
 use fvm::executor::{ApplyKind, ApplyRet, DefaultExecutor, Executor};
 use fvm::machine::{DefaultMachine, Machine, MachineContext};
-use fvm::trace::ExecutionEvent;
+use fvm::trace::{ExecutionEvent, IpldOperation};
 use fvm_ipld_blockstore::Blockstore;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
+use fvm_shared::event::StampedEvent;
 use fvm_shared::message::Message;
 use fvm_shared::ActorID;
 use fvm_workbench_api::trace::ExecutionEvent::{
-    Call, CallError, CallReturn, GasCharge, InvokeActor,
+    Call, CallError, CallReturn, GasCharge, InvokeActor, Ipld, Unrecognized,
 };
-use fvm_workbench_api::trace::ExecutionTrace;
+use fvm_workbench_api::trace::{self, ExecutionTrace};
 use fvm_workbench_api::{bench::Bench, ExecutionResult};
 use vm_api::ActorState;
 
@@ -25,7 +26,9 @@ use crate::externs::FakeExterns;
 
 pub use self::kernel::BenchKernel;
 
+pub mod event;
 pub mod kernel;
+pub mod span;
 
 /// A workbench instance backed by a real FVM.
 pub struct FvmBench<B>
@@ -46,6 +49,19 @@ where
         Self { executor }
     }
 
+    // Applies a message, converting the result along with the events the kernel recorded
+    // during its execution.
+    fn apply(
+        &mut self,
+        msg: Message,
+        kind: ApplyKind,
+        msg_length: usize,
+    ) -> anyhow::Result<ExecutionResult> {
+        let ret = self.executor.execute_message(msg, kind, msg_length);
+        let recorded = event::take_events();
+        ret.map(|ret| ret_as_result(ret, recorded))
+    }
+
     fn modify_machine_ctx<F>(&mut self, modify_ctx: F)
     where
         F: Fn(&mut MachineContext),
@@ -89,7 +105,7 @@ where
     B: Blockstore + Clone,
 {
     fn execute(&mut self, msg: Message, msg_length: usize) -> anyhow::Result<ExecutionResult> {
-        self.executor.execute_message(msg, ApplyKind::Explicit, msg_length).map(ret_as_result)
+        self.apply(msg, ApplyKind::Explicit, msg_length)
     }
 
     fn execute_implicit(
@@ -97,7 +113,7 @@ where
         msg: Message,
         msg_length: usize,
     ) -> anyhow::Result<ExecutionResult> {
-        self.executor.execute_message(msg, ApplyKind::Implicit, msg_length).map(ret_as_result)
+        self.apply(msg, ApplyKind::Implicit, msg_length)
     }
     fn store(&self) -> &dyn Blockstore {
         self.executor.blockstore()
@@ -310,7 +326,8 @@ where
 }
 
 // Converts an FVM-internal application result to an API execution result.
-fn ret_as_result(ret: ApplyRet) -> ExecutionResult {
+// Events recorded by the kernel are placed in the trace where they were emitted.
+fn ret_as_result(ret: ApplyRet, recorded: Vec<StampedEvent>) -> ExecutionResult {
     ExecutionResult {
         receipt: ret.msg_receipt,
         penalty: ret.penalty,
@@ -318,13 +335,18 @@ fn ret_as_result(ret: ApplyRet) -> ExecutionResult {
         gas_burned: ret.gas_burned,
         base_fee_burn: ret.base_fee_burn,
         over_estimation_burn: ret.over_estimation_burn,
-        trace: trace_as_trace(ret.exec_trace),
+        events: ret.events,
+        trace: trace_as_trace(ret.exec_trace, recorded),
         message: ret.failure_info.map_or("".to_string(), |f| f.to_string()),
     }
 }
 
 // Converts an FVM-internal trace to a workbench API trace.
-fn trace_as_trace(fvm_trace: fvm::trace::ExecutionTrace) -> ExecutionTrace {
+fn trace_as_trace(
+    fvm_trace: fvm::trace::ExecutionTrace,
+    recorded: Vec<StampedEvent>,
+) -> ExecutionTrace {
+    let mut recorded = recorded.into_iter();
     let mut events = Vec::new();
     for e in fvm_trace {
         match e {
@@ -332,6 +354,7 @@ fn trace_as_trace(fvm_trace: fvm::trace::ExecutionTrace) -> ExecutionTrace {
                 name: e.name,
                 compute_milli: e.compute_gas.as_milligas(),
                 other_milli: e.other_gas.as_milligas(),
+                elapsed: e.elapsed.get().copied(),
             }),
             ExecutionEvent::Call { from, to, method, params, value, gas_limit, read_only } => {
                 events.push(Call { from, to, method, params, value, gas_limit, read_only })
@@ -341,7 +364,20 @@ fn trace_as_trace(fvm_trace: fvm::trace::ExecutionTrace) -> ExecutionTrace {
             }
             ExecutionEvent::CallError(e) => events.push(CallError { reason: e.0, errno: e.1 }),
             ExecutionEvent::InvokeActor(cid) => events.push(InvokeActor { cid }),
-            _ => todo!(),
+            ExecutionEvent::Ipld { op, cid, size } => {
+                let op = match op {
+                    IpldOperation::Get => trace::IpldOperation::Get,
+                    IpldOperation::Put => trace::IpldOperation::Put,
+                };
+                events.push(Ipld { op, cid, size })
+            }
+            ExecutionEvent::Log(msg) => match event::untag_event(&msg, &mut recorded) {
+                Some(actor_event) => events.push(actor_event),
+                None => events.push(span::untag_log(msg)),
+            },
+            // Events this version of the workbench doesn't model are kept opaquely, so that
+            // newer FVM versions degrade gracefully rather than failing the execution.
+            e => events.push(Unrecognized { description: format!("{:?}", e) }),
         }
     }
     ExecutionTrace::new(events)
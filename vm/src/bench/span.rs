@@ -0,0 +1,125 @@
+//! Named spans delimited by actor log messages.
+//!
+//! Actor code opens a span by logging a message containing `SpanStart:<label>`, optionally
+//! followed by `key=value` attributes, and closes it by logging `SpanEnd:<label>`.
+//! The kernel recognises these messages and tags them with a reserved prefix before they are
+//! recorded in the FVM trace. Conversion of the FVM trace then turns tagged messages into
+//! first-class span events, and leaves all other messages as plain logs.
+
+use fvm_workbench_api::trace::ExecutionEvent;
+
+/// Pattern in an actor log message that opens a named span.
+pub const SPAN_START_PATTERN: &str = "SpanStart:";
+/// Pattern in an actor log message that closes a named span.
+pub const SPAN_END_PATTERN: &str = "SpanEnd:";
+
+// Reserved prefix for log messages tagged by the kernel.
+// Actor messages that happen to start with this prefix, or with the escape, are escaped so they
+// can't be mistaken for tagged messages, and are restored exactly when untagged.
+const TAG_PREFIX: &str = "\u{0}workbench:";
+const START_TAG: &str = "\u{0}workbench:span-start:";
+const END_TAG: &str = "\u{0}workbench:span-end:";
+const ESCAPE: &str = "\\";
+
+/// Tags an actor log message that delimits a span, and escapes any other message that could be
+/// mistaken for a tagged one.
+pub fn tag_log(msg: String) -> String {
+    if let Some(idx) = msg.find(SPAN_START_PATTERN) {
+        format!("{}{}", START_TAG, msg[idx + SPAN_START_PATTERN.len()..].trim())
+    } else if let Some(idx) = msg.find(SPAN_END_PATTERN) {
+        format!("{}{}", END_TAG, msg[idx + SPAN_END_PATTERN.len()..].trim())
+    } else if msg.starts_with(TAG_PREFIX) || msg.starts_with(ESCAPE) {
+        format!("{}{}", ESCAPE, msg)
+    } else {
+        msg
+    }
+}
+
+/// Converts a log message recorded in the FVM trace to a workbench trace event.
+pub fn untag_log(msg: String) -> ExecutionEvent {
+    if let Some(rest) = msg.strip_prefix(START_TAG) {
+        let (label, attrs) = parse_span_start(rest);
+        ExecutionEvent::SpanStart { label, attrs }
+    } else if let Some(label) = msg.strip_prefix(END_TAG) {
+        ExecutionEvent::SpanEnd { label: label.to_string() }
+    } else if let Some(escaped) = msg.strip_prefix(ESCAPE) {
+        ExecutionEvent::Log { msg: escaped.to_string() }
+    } else {
+        ExecutionEvent::Log { msg }
+    }
+}
+
+// Parses "<label> [key=value]*" into the label and attributes.
+// Trailing whitespace-separated tokens that contain '=' are attributes, and the rest is the label.
+// The label is never empty: if every token contains '=', the first is the label.
+fn parse_span_start(s: &str) -> (String, Vec<(String, String)>) {
+    let tokens: Vec<&str> = s.split_whitespace().collect();
+    let label_len =
+        tokens.iter().rposition(|t| !t.contains('=')).map_or(tokens.len().min(1), |i| i + 1);
+    let label = tokens[..label_len].join(" ");
+    let attrs = tokens[label_len..]
+        .iter()
+        .map(|t| {
+            let (k, v) = t.split_once('=').unwrap();
+            (k.to_string(), v.to_string())
+        })
+        .collect();
+    (label, attrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_round_trip_through_tagging() {
+        for msg in [
+            "plain message",
+            "",
+            "\\",
+            "\\plain",
+            "\\\\double",
+            "\u{0}workbench:span-start:fake",
+            "\\\u{0}workbench:span-end:fake",
+        ] {
+            let untagged = untag_log(tag_log(msg.to_string()));
+            assert_eq!(ExecutionEvent::Log { msg: msg.to_string() }, untagged);
+        }
+    }
+
+    #[test]
+    fn spans_tagged_from_patterns() {
+        assert_eq!(
+            ExecutionEvent::SpanStart {
+                label: "load_deals".to_string(),
+                attrs: vec![kv("n", "3"), kv("epoch", "10")],
+            },
+            untag_log(tag_log("load: SpanStart: load_deals n=3 epoch=10".to_string()))
+        );
+        assert_eq!(
+            ExecutionEvent::SpanEnd { label: "load_deals".to_string() },
+            untag_log(tag_log("SpanEnd:load_deals ".to_string()))
+        );
+    }
+
+    #[test]
+    fn span_attributes_parsed() {
+        assert_eq!(("verify deals".to_string(), vec![]), parse_span_start("verify  deals"));
+        assert_eq!(
+            ("verify".to_string(), vec![kv("n", "2"), kv("x", "a=b")]),
+            parse_span_start("verify n=2 x=a=b")
+        );
+        // Only trailing tokens are attributes.
+        assert_eq!(
+            ("a=1 verify".to_string(), vec![kv("n", "")]),
+            parse_span_start("a=1 verify n=")
+        );
+        // The label is kept even if it looks like an attribute.
+        assert_eq!(("k=v".to_string(), vec![kv("n", "1")]), parse_span_start("k=v n=1"));
+        assert_eq!(("".to_string(), vec![]), parse_span_start(""));
+    }
+
+    fn kv(k: &str, v: &str) -> (String, String) {
+        (k.to_string(), v.to_string())
+    }
+}